@@ -0,0 +1,175 @@
+/*
+ * C interface of relaxed_ik_core (generated by cbindgen from src/relaxed_ik_wrapper.rs, do not edit by hand).
+ *
+ * Functions returning int return RELAXED_IK_OK (0) on success or one of the negative RELAXED_IK_ERR_* codes;
+ * on failure last_error_message() describes what went wrong. Nothing panics or aborts across this boundary.
+ *
+ * Buffers returned in an Opt (by solve and relaxed_ik_solve) are owned by the caller and must be released with free_opt.
+ * Strings returned by last_error_message are owned by the library, are per thread, and stay valid until the next
+ * failing call on the same thread.
+ *
+ * Quaternions are passed as (x, y, z, w).
+ */
+
+#ifndef RELAXED_IK_H
+#define RELAXED_IK_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define RELAXED_IK_OK 0
+
+#define RELAXED_IK_ERR_NULL_POINTER -1
+
+#define RELAXED_IK_ERR_INVALID_ARGUMENT -2
+
+#define RELAXED_IK_ERR_CONFIG -3
+
+#define RELAXED_IK_ERR_INVALID_GOALS -4
+
+#define RELAXED_IK_ERR_SOLVER -5
+
+#define RELAXED_IK_ERR_PANIC -6
+
+typedef struct RelaxedIKHandle RelaxedIKHandle;
+
+typedef struct Opt {
+  const double *data;
+  int length;
+} Opt;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+const char *last_error_message(void);
+
+struct RelaxedIKHandle *relaxed_ik_new(const char *path);
+
+void relaxed_ik_free(struct RelaxedIKHandle *handle);
+
+int relaxed_ik_dynamic_obstacle_cb(struct RelaxedIKHandle *handle,
+                                   const char *name,
+                                   const double *pos_arr,
+                                   const double *quat_arr);
+
+int relaxed_ik_solve(struct RelaxedIKHandle *handle,
+                     const double *pos_arr,
+                     int pos_length,
+                     const double *quat_arr,
+                     int quat_length,
+                     struct Opt *out);
+
+int relaxed_ik_solve_into(struct RelaxedIKHandle *handle,
+                          const double *pos_arr,
+                          int pos_length,
+                          const double *quat_arr,
+                          int quat_length,
+                          double *out_arr,
+                          int out_length);
+
+int relaxed_ik_solve_within(struct RelaxedIKHandle *handle,
+                            const double *pos_arr,
+                            int pos_length,
+                            const double *quat_arr,
+                            int quat_length,
+                            double time_budget,
+                            double *out_arr,
+                            int out_length,
+                            int *deadline_hit);
+
+int relaxed_ik_solve_precise(struct RelaxedIKHandle *handle,
+                             const double *pos_arr,
+                             int pos_length,
+                             const double *quat_arr,
+                             int quat_length,
+                             struct Opt *out);
+
+int relaxed_ik_solve_precise_with_options(struct RelaxedIKHandle *handle,
+                                          const double *pos_arr,
+                                          int pos_length,
+                                          const double *quat_arr,
+                                          int quat_length,
+                                          double pos_tolerance,
+                                          double rot_tolerance,
+                                          int max_restarts,
+                                          double time_limit,
+                                          double *out_arr,
+                                          int out_length,
+                                          int *found,
+                                          int *attempt);
+
+int relaxed_ik_solve_randstart(struct RelaxedIKHandle *handle,
+                               const double *pos_arr,
+                               int pos_length,
+                               const double *quat_arr,
+                               int quat_length,
+                               struct Opt *out,
+                               int *success);
+
+int relaxed_ik_link_poses(struct RelaxedIKHandle *handle,
+                          const double *x_arr,
+                          int x_length,
+                          double *out_arr,
+                          int out_length);
+
+int relaxed_ik_forward_kinematics(struct RelaxedIKHandle *handle,
+                                  const double *x_arr,
+                                  int x_length,
+                                  double *pos_out,
+                                  int pos_out_length,
+                                  double *quat_out,
+                                  int quat_out_length);
+
+int relaxed_ik_reset(struct RelaxedIKHandle *handle, const double *x_arr, int x_length);
+
+int relaxed_ik_set_objective_mode(struct RelaxedIKHandle *handle, const char *objective_mode);
+
+int relaxed_ik_set_goal_frames(struct RelaxedIKHandle *handle,
+                               const char *position_mode,
+                               const char *rotation_mode);
+
+int relaxed_ik_set_tcp(struct RelaxedIKHandle *handle,
+                       int chain,
+                       const double *pos_arr,
+                       const double *quat_arr);
+
+int relaxed_ik_num_dof(struct RelaxedIKHandle *handle);
+
+int relaxed_ik_num_chains(struct RelaxedIKHandle *handle);
+
+int relaxed_ik_num_link_poses(struct RelaxedIKHandle *handle);
+
+const char *relaxed_ik_link_name(struct RelaxedIKHandle *handle, int link_idx);
+
+const char *relaxed_ik_joint_name(struct RelaxedIKHandle *handle, int joint_idx);
+
+int relaxed_ik_add_sphere_obstacle(struct RelaxedIKHandle *handle,
+                                   const char *name,
+                                   double radius,
+                                   const double *pos_arr,
+                                   int is_dynamic);
+
+int relaxed_ik_add_cuboid_obstacle(struct RelaxedIKHandle *handle,
+                                   const char *name,
+                                   const double *half_extents_arr,
+                                   const double *pos_arr,
+                                   const double *quat_arr,
+                                   int is_dynamic);
+
+int relaxed_ik_remove_obstacle(struct RelaxedIKHandle *handle, const char *name);
+
+void free_opt(struct Opt opt);
+
+void dynamic_obstacle_cb(const char *name, const double *pos_arr, const double *quat_arr);
+
+struct Opt solve(const double *pos_arr, int pos_length, const double *quat_arr, int quat_length);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* RELAXED_IK_H */
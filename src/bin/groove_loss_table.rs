@@ -0,0 +1,61 @@
+extern crate relaxed_ik_lib;
+use relaxed_ik_lib::relaxed_ik;
+use std::env;
+
+// prints the groove loss curve of one objective of the robot loaded in settings.yaml as csv, for plotting while tuning.
+// usage: groove_loss_table <objective name> [x_min] [x_max] [num_samples]
+// without arguments, lists the objectives and their groove loss parameters
+fn main() {
+    let r = match relaxed_ik::RelaxedIK::from_loaded(1) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Failed to load the robot in settings.yaml: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        for i in 0..r.om.names.len() {
+            match r.om.objectives[i].groove_loss_params() {
+                Some(p) => println!("{} (weight {}): t {} d {} c {} f {} g {}", r.om.names[i], r.om.weight_priors[i], p.t, p.d, p.c, p.f, p.g),
+                None => println!("{} (weight {}): no groove loss", r.om.names[i], r.om.weight_priors[i])
+            }
+        }
+        return;
+    }
+
+    let params = match r.om.get_groove_loss(args[1].as_str()) {
+        Some(p) => p,
+        None => {
+            eprintln!("No objective named {} with a groove loss, run without arguments to list them", args[1]);
+            std::process::exit(1);
+        }
+    };
+    let x_min = match args.get(2).map(|a| a.parse::<f64>()) {
+        None => params.t - 1.0,
+        Some(Ok(v)) => v,
+        Some(Err(_)) => usage_error("x_min should be a number")
+    };
+    let x_max = match args.get(3).map(|a| a.parse::<f64>()) {
+        None => params.t + 1.0,
+        Some(Ok(v)) => v,
+        Some(Err(_)) => usage_error("x_max should be a number")
+    };
+    let num_samples = match args.get(4).map(|a| a.parse::<usize>()) {
+        None => 101,
+        Some(Ok(n)) => n,
+        Some(Err(_)) => usage_error("num_samples should be a non-negative integer")
+    };
+
+    println!("x,loss,derivative");
+    for (x_val, loss, derivative) in params.tabulate(x_min, x_max, num_samples) {
+        println!("{},{},{}", x_val, loss, derivative);
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("usage: groove_loss_table <objective name> [x_min] [x_max] [num_samples]");
+    std::process::exit(2);
+}
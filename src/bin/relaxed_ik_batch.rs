@@ -0,0 +1,118 @@
+extern crate relaxed_ik_lib;
+use relaxed_ik_lib::relaxed_ik::RelaxedIK;
+use relaxed_ik_lib::utils_rust::trajectory_utils::read_goal_list;
+use relaxed_ik_lib::utils_rust::subscriber_utils::GoalFrame;
+use relaxed_ik_lib::utils_rust::error_utils::RelaxedIKError;
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+// solves a list of goal poses without any prompts and writes one csv row per goal: the joint values, then each chain's
+// position and rotation error, the objective value, whether the last solve converged and the total solve time.
+// usage: relaxed_ik_batch <info file> <goal file> <output csv> [--independent] [--absolute] [--solves N]
+//   info file: a name in config/info_files, or a path to one
+//   goal file: .csv or .jsonl, see read_goal_list in trajectory_utils
+//   --independent: start every goal from the info file's starting config, instead of from the previous solution
+//   --absolute: goals are poses in the robot's base frame, instead of offsets from the starting pose
+//   --solves N: solve calls per goal (default 1). more calls let the solver travel further from where it starts
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 4 {
+        eprintln!("usage: relaxed_ik_batch <info file> <goal file> <output csv> [--independent] [--absolute] [--solves N]");
+        eprintln!("goal file, one goal per line:");
+        eprintln!("  .csv: x y z qx qy qz qw for each chain");
+        eprintln!("  .jsonl: {{\"poses\": [{{\"position\": [x, y, z], \"orientation\": [qx, qy, qz, qw]}}, ...]}}");
+        std::process::exit(2);
+    }
+
+    let mut independent = false;
+    let mut absolute = false;
+    let mut solves_per_goal: usize = 1;
+    let mut i = 4;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--independent" => independent = true,
+            "--absolute" => absolute = true,
+            "--solves" => {
+                i += 1;
+                solves_per_goal = match args.get(i).map(|a| a.parse::<usize>()) {
+                    Some(Ok(n)) if n > 0 => n,
+                    _ => {
+                        eprintln!("--solves should be followed by a positive integer");
+                        std::process::exit(2);
+                    }
+                };
+            },
+            other => {
+                eprintln!("unknown option {}", other);
+                std::process::exit(2);
+            }
+        }
+        i += 1;
+    }
+
+    if let Err(e) = run(&args[1], &args[2], &args[3], independent, absolute, solves_per_goal) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(info_file: &str, goal_file: &str, output: &str, independent: bool, absolute: bool, solves_per_goal: usize) -> Result<(), RelaxedIKError> {
+    let mut r = if Path::new(info_file).is_file() {
+        RelaxedIK::from_yaml_path(info_file.to_string(), 1)?
+    } else {
+        RelaxedIK::from_info_file_name(info_file.to_string(), 1)?
+    };
+    if absolute {
+        r.vars.position_mode = GoalFrame::Absolute;
+        r.vars.rotation_mode = GoalFrame::Absolute;
+    }
+    let goals = read_goal_list(goal_file.to_string())?;
+    let num_chains = r.vars.robot.num_chains;
+
+    let file = File::create(output).map_err(|e| RelaxedIKError::Io{path: output.to_string(), source: e})?;
+    let mut writer = BufWriter::new(file);
+    let mut header: Vec<String> = vec!["index".to_string()];
+    header.extend(r.vars.robot.joint_ordering.iter().cloned());
+    for c in 0..num_chains {
+        header.push(format!("pos_error_{}", c));
+        header.push(format!("rot_error_{}", c));
+    }
+    header.extend(vec!["objective_value".to_string(), "converged".to_string(), "solve_time_ms".to_string()]);
+    write_line(&mut writer, output, header.join(","))?;
+
+    let init_state = r.vars.init_state.clone();
+    for g in 0..goals.len() {
+        if independent {
+            r.reset(init_state.clone())?;
+        }
+        let mut solve_time = Duration::from_secs(0);
+        let mut res = None;
+        for _ in 0..solves_per_goal {
+            let step = r.solve(&goals[g]).map_err(|e| { eprintln!("Failed on goal {}", g); e })?;
+            solve_time += step.solve_time;
+            res = Some(step);
+        }
+        let res = res.unwrap();
+
+        let mut row: Vec<String> = vec![g.to_string()];
+        row.extend(res.x.iter().map(|v| v.to_string()));
+        for c in 0..num_chains {
+            row.push(res.pos_errors[c].to_string());
+            row.push(res.rot_errors[c].to_string());
+        }
+        row.push(res.objective_value.to_string());
+        row.push(res.converged.to_string());
+        row.push((solve_time.as_secs_f64() * 1000.0).to_string());
+        write_line(&mut writer, output, row.join(","))?;
+    }
+    writer.flush().map_err(|e| RelaxedIKError::Io{path: output.to_string(), source: e})?;
+    println!("Solved {} goals, wrote {}", goals.len(), output);
+    Ok(())
+}
+
+fn write_line(writer: &mut BufWriter<File>, output: &str, line: String) -> Result<(), RelaxedIKError> {
+    writeln!(writer, "{}", line).map_err(|e| RelaxedIKError::Io{path: output.to_string(), source: e})
+}
@@ -1,6 +1,10 @@
 extern crate relaxed_ik_lib;
 use relaxed_ik_lib::relaxed_ik;
 use relaxed_ik_lib::utils_rust::subscriber_utils::EEPoseGoalsSubscriber;
+use relaxed_ik_lib::utils_rust::file_utils::ConfigPaths;
+use relaxed_ik_lib::utils_rust::yaml_utils::SettingsParser;
+use relaxed_ik_lib::utils_rust::trajectory_utils::CartesianTrajectory;
+use relaxed_ik_lib::utils_rust::error_utils::RelaxedIKError;
 use std::sync::{Arc, Mutex};
 use nalgebra::{Vector3, UnitQuaternion, Quaternion};
 
@@ -14,7 +18,29 @@ fn main() {
     io::stdin().read_line(&mut name_buf).expect("Failed to read line");
     let name: String = name_buf.trim().to_string();
 
-    let mut r = relaxed_ik::RelaxedIK::from_info_file_name(format!("{}_info.yaml", name), 1);
+    let paths = ConfigPaths::from_env();
+    let settings = match SettingsParser::from_yaml_path_with_paths(paths.settings(), &paths) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut r = match relaxed_ik::RelaxedIK::from_settings(paths.info_file(format!("{}_info.yaml", name).as_str()), 1, &paths, &settings) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Failed to load {}: {}", name, e);
+            std::process::exit(1);
+        }
+    };
+
+    if settings.input_device != "keyboard" {
+        if let Err(e) = play_animation_file(&mut r, &paths, &settings) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
     let arc = Arc::new(Mutex::new(EEPoseGoalsSubscriber::new()));
     let mut g = arc.lock().unwrap();
@@ -41,9 +67,28 @@ fn main() {
     let rate = time::Duration::from_millis(10);
 
     loop {
-        let x = r.solve(&g);
-        println!("{:?}", x);
+        match r.solve(&g) {
+            Ok(res) => println!("{:?}", res.x),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
 
         thread::sleep(rate);
     }
 }
+
+fn play_animation_file(r: &mut relaxed_ik::RelaxedIK, paths: &ConfigPaths, settings: &SettingsParser) -> Result<(), RelaxedIKError> {
+    let file_name = settings.input_device.as_str();
+    let trajectory = CartesianTrajectory::from_path(paths.animation_file(file_name))?;
+    let rate = settings.playback_rate;
+    let output = settings.trajectory_output.clone();
+
+    println!("Playing {} ({} waypoints, {} s) at {} solves per second...", file_name, trajectory.waypoints.len(),
+        trajectory.end_time() - trajectory.start_time(), rate);
+    let joint_trajectory = r.play_trajectory(&trajectory, rate)?;
+    joint_trajectory.write_csv(output.clone())?;
+    println!("Wrote {} joint states to {}", joint_trajectory.times.len(), output);
+    Ok(())
+}
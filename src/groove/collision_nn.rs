@@ -1,6 +1,7 @@
 use crate::utils_rust::yaml_utils::NeuralNetParser;
 use crate::utils_rust::{geometry_utils, yaml_utils};
 use crate::spacetime::robot::Robot;
+use crate::utils_rust::error_utils::RelaxedIKError;
 use nalgebra::{DMatrix, DVector};
 
 fn relu(x: f64) -> f64 {
@@ -63,18 +64,23 @@ pub struct CollisionNN {
 }
 
 impl CollisionNN {
-    pub fn from_yaml_path(fp: String) -> Self {
-        let parser = NeuralNetParser::from_yaml_path(fp.clone());
-        let input_length = parser.coefs[0].len();
+    pub fn from_yaml_path(fp: String) -> Result<Self, RelaxedIKError> {
+        let parser = NeuralNetParser::from_yaml_path(fp.clone())?;
+        Ok(CollisionNN::new(parser.coef_matrices.clone(), parser.intercept_vectors.clone(), parser.split_point))
+    }
+
+    // one (inputs x outputs) coefficient matrix and (1 x outputs) intercept row per layer
+    pub fn new(coef_matrices: Vec<DMatrix<f64>>, intercept_vectors: Vec<DMatrix<f64>>, split_point: f64) -> Self {
+        let input_length = coef_matrices[0].nrows();
         let __x_proxy: DMatrix<f64> = DMatrix::from_element(1, input_length, 0.0);
         let mut __intermediate_vecs: Vec<DMatrix<f64>> = Vec::new();
         let mut result = 0.0;
 
-        for i in 0..parser.intercept_vectors.len() {
-            __intermediate_vecs.push(parser.intercept_vectors[i].clone());
+        for i in 0..intercept_vectors.len() {
+            __intermediate_vecs.push(intercept_vectors[i].clone());
         }
 
-        Self{coef_matrices: parser.coef_matrices.clone(), intercept_vectors: parser.intercept_vectors.clone(), split_point: parser.split_point, input_length, result, __x_proxy, __intermediate_vecs}
+        Self{coef_matrices, intercept_vectors, split_point, input_length, result, __x_proxy, __intermediate_vecs}
     }
 
     pub fn predict_mutable(&mut self, x: Vec<f64>) {
@@ -199,8 +205,8 @@ pub struct CollisionNNJointPoint {
 }
 
 impl CollisionNNJointPoint {
-    pub fn from_yaml_path(fp: String) -> Self {
-        let parser = NeuralNetParser::from_yaml_path(fp.clone());
+    pub fn from_yaml_path(fp: String) -> Result<Self, RelaxedIKError> {
+        let parser = NeuralNetParser::from_yaml_path(fp.clone())?;
         let input_length = parser.coefs[0].len();
         let __x_proxy: DMatrix<f64> = DMatrix::from_element(1, input_length, 0.0);
         let mut __intermediate_vecs: Vec<DMatrix<f64>> = Vec::new();
@@ -210,7 +216,7 @@ impl CollisionNNJointPoint {
             __intermediate_vecs.push(parser.intercept_vectors[i].clone());
         }
 
-        Self{coef_matrices: parser.coef_matrices.clone(), intercept_vectors: parser.intercept_vectors.clone(), split_point: parser.split_point, input_length, result, __x_proxy, __intermediate_vecs}
+        Ok(Self{coef_matrices: parser.coef_matrices.clone(), intercept_vectors: parser.intercept_vectors.clone(), split_point: parser.split_point, input_length, result, __x_proxy, __intermediate_vecs})
     }
 
     pub fn predict(&self, x: &Vec<f64>, robot: &Robot) -> f64 {
@@ -1,4 +1,6 @@
 use crate::utils_rust::yaml_utils::EnvCollisionFileParser;
+use crate::utils_rust::error_utils::RelaxedIKError;
+use crate::utils_rust::shape_parser_utils::ObstacleAnimation;
 use nalgebra::{Vector3, Isometry3, Point3};
 use nalgebra::geometry::{Translation3, UnitQuaternion, Quaternion};
 use ncollide3d::pipeline::{*};
@@ -42,17 +44,20 @@ pub struct RelaxedIKEnvCollision {
     pub dyn_obstacle_handles: Vec<(CollisionObjectSlabHandle, String)>,
     pub active_pairs: Vec<BTreeMap<CollisionObjectSlabHandle, Vec<CollisionObjectSlabHandle>>>,
     pub active_obstacles: Vec<Vec<(Option<CollisionObjectSlabHandle>, f64)>>,
+    // (obstacle, its pose at time 0, animation), moved by set_animation_time
+    pub animations: Vec<(CollisionObjectSlabHandle, Isometry3<f64>, ObstacleAnimation)>,
 }
 
 impl RelaxedIKEnvCollision {
     pub fn init_collision_world (
         env_collision_file: EnvCollisionFileParser,
         frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>,
-    ) -> Self {
+    ) -> Result<Self, RelaxedIKError> {
         let link_radius = env_collision_file.robot_link_radius;
         let plane_obstacles = env_collision_file.cuboids;
         let sphere_obstacles = env_collision_file.spheres;
         let pcd_obstacles = env_collision_file.pcds;
+        let animations = env_collision_file.animations;
 
         // The links are part of group 1 and can only interact with obstacles
         let mut link_groups = CollisionGroups::new();
@@ -60,13 +65,8 @@ impl RelaxedIKEnvCollision {
         link_groups.set_blacklist(&[1]);
         link_groups.set_whitelist(&[2]);
 
-        // All the other objects are part of the group 2 and interact only with the links
-        let mut others_groups = CollisionGroups::new();
-        others_groups.set_membership(&[2]);
-        others_groups.set_blacklist(&[2]);
-        others_groups.set_whitelist(&[1]);
-
-        let proximity_query = GeometricQueryType::Proximity(20.0 * link_radius);
+        let others_groups = RelaxedIKEnvCollision::obstacle_groups();
+        let proximity_query = RelaxedIKEnvCollision::proximity_query(link_radius);
 
         let mut world = CollisionWorld::new(0.0);
         let mut link_handles: Vec<Vec<CollisionObjectSlabHandle>> = Vec::new();
@@ -135,7 +135,14 @@ impl RelaxedIKEnvCollision {
                 points.push(Point3::new(sphere_obs.tx, sphere_obs.ty, sphere_obs.tz));
             }
             // let pcd = ShapeHandle::new(Compound::new(shapes));
-            let pcd = ShapeHandle::new(ConvexHull::try_from_points(&points).unwrap());
+            if points.len() < 4 {
+                return Err(RelaxedIKError::InvalidObstacle{name: pcd_obs.name.clone(), message: format!("a point cloud needs at least 4 points, got {}", points.len())});
+            }
+            let hull = match ConvexHull::try_from_points(&points) {
+                Some(h) => h,
+                None => return Err(RelaxedIKError::InvalidObstacle{name: pcd_obs.name.clone(), message: "could not build a convex hull from the point cloud".to_string()})
+            };
+            let pcd = ShapeHandle::new(hull);
             let pcd_ts = Translation3::new(pcd_obs.tx, pcd_obs.ty, pcd_obs.tz);
             let pcd_rot = UnitQuaternion::from_euler_angles(pcd_obs.rx, pcd_obs.ry, pcd_obs.rz);
             let pcd_pos = Isometry3::from_parts(pcd_ts, pcd_rot);
@@ -146,7 +153,100 @@ impl RelaxedIKEnvCollision {
             }
         }
         
-        return Self{world, link_radius, link_handles, dyn_obstacle_handles, active_pairs, active_obstacles};
+        let mut env_collision = Self{world, link_radius, link_handles, dyn_obstacle_handles, active_pairs, active_obstacles, animations: Vec::new()};
+        for animation in animations {
+            env_collision.add_animation(animation)?;
+        }
+        return Ok(env_collision);
+    }
+
+    // All the other objects are part of the group 2 and interact only with the links
+    fn obstacle_groups() -> CollisionGroups {
+        let mut others_groups = CollisionGroups::new();
+        others_groups.set_membership(&[2]);
+        others_groups.set_blacklist(&[2]);
+        others_groups.set_whitelist(&[1]);
+        others_groups
+    }
+
+    fn proximity_query(link_radius: f64) -> GeometricQueryType<f64> {
+        GeometricQueryType::Proximity(20.0 * link_radius)
+    }
+
+    fn find_obstacle(&self, name: &str) -> Option<CollisionObjectSlabHandle> {
+        for (handle, co) in self.world.collision_objects() {
+            if !co.data().link_data.is_link && co.data().name == name {
+                return Some(handle);
+            }
+        }
+        None
+    }
+
+    // current pose of the obstacle called name, in the robot's base frame
+    pub fn obstacle_pose(&self, name: &str) -> Option<Isometry3<f64>> {
+        let handle = self.find_obstacle(name)?;
+        Some(self.world.objects.get(handle).unwrap().position().clone())
+    }
+
+    // adds an obstacle after the world was built, dynamic ones can then be moved with update_dynamic_obstacle
+    pub fn add_obstacle(
+        &mut self,
+        name: &str,
+        shape: ShapeHandle<f64>,
+        position: Isometry3<f64>,
+        is_dynamic: bool,
+    ) -> Result<(), RelaxedIKError> {
+        if self.find_obstacle(name).is_some() {
+            return Err(RelaxedIKError::InvalidObstacle{name: name.to_string(), message: "an obstacle with this name already exists".to_string()});
+        }
+        let data = CollisionObjectData::new(name.to_string(), LinkData::new(false, -1));
+        let proximity_query = RelaxedIKEnvCollision::proximity_query(self.link_radius);
+        let handle = self.world.add(position, shape, RelaxedIKEnvCollision::obstacle_groups(), proximity_query, data);
+        if is_dynamic {
+            self.dyn_obstacle_handles.push((handle.0, name.to_string()));
+        }
+        Ok(())
+    }
+
+    // the obstacle must be dynamic, its current pose becomes the one the animation's offsets are applied to
+    pub fn add_animation(&mut self, animation: ObstacleAnimation) -> Result<(), RelaxedIKError> {
+        let handle = match self.find_obstacle(animation.name.as_str()) {
+            Some(h) => h,
+            None => return Err(RelaxedIKError::InvalidObstacle{name: animation.name.clone(), message: "no obstacle with this name to animate".to_string()})
+        };
+        if !self.dyn_obstacle_handles.iter().any(|(h, _)| *h == handle) {
+            return Err(RelaxedIKError::InvalidObstacle{name: animation.name.clone(), message: "only dynamic obstacles can be animated".to_string()});
+        }
+        self.animations.retain(|(h, _, _)| *h != handle);
+        let base = self.world.objects.get(handle).unwrap().position().clone();
+        self.animations.push((handle, base, animation));
+        Ok(())
+    }
+
+    // moves every animated obstacle to where its animation puts it at time t (seconds)
+    pub fn set_animation_time(&mut self, t: f64) {
+        for (handle, base, animation) in &self.animations {
+            let (offset, rotation) = animation.offset_at(t);
+            let position = Isometry3::from_parts(Translation3::from(base.translation.vector + offset), rotation * base.rotation);
+            let co = self.world.objects.get_mut(*handle).unwrap();
+            co.set_position(position);
+        }
+    }
+
+    pub fn remove_obstacle(&mut self, name: &str) -> Result<(), RelaxedIKError> {
+        let handle = match self.find_obstacle(name) {
+            Some(h) => h,
+            None => return Err(RelaxedIKError::InvalidObstacle{name: name.to_string(), message: "no obstacle with this name".to_string()})
+        };
+        self.world.remove(&[handle]);
+        // handles are recycled by the world, so drop every reference to this one
+        self.dyn_obstacle_handles.retain(|(h, _)| *h != handle);
+        self.animations.retain(|(h, _, _)| *h != handle);
+        for arm_idx in 0..self.active_pairs.len() {
+            self.active_pairs[arm_idx].remove(&handle);
+            self.active_obstacles[arm_idx].retain(|(h, _)| *h != Some(handle));
+        }
+        Ok(())
     }
 
     pub fn update_links(
@@ -1,7 +1,9 @@
 use crate::groove::gradient::{ForwardFiniteDiff, CentralFiniteDiff, GradientFinder, ForwardFiniteDiffImmutable, CentralFiniteDiffImmutable, GradientFinderImmutable};
 use crate::groove::vars::{RelaxedIKVars};
 use optimization_engine::{constraints::*, panoc::*, *};
+use optimization_engine::core::SolverStatus;
 use nlopt::*;
+use std::time::Duration;
 use crate::groove::objective_master::ObjectiveMaster;
 
 pub struct OptimizationEngineOpen {
@@ -14,7 +16,9 @@ impl OptimizationEngineOpen {
         OptimizationEngineOpen { dim, cache }
     }
 
-    pub fn optimize(&mut self, x: &mut [f64], v: &RelaxedIKVars, om: &ObjectiveMaster, max_iter: usize) {
+    // with max_duration, the iterate reached when it runs out is returned, with an exit status of NotConvergedOutOfTime.
+    // the time is checked between iterations, so one iteration can run past it
+    pub fn optimize(&mut self, x: &mut [f64], v: &RelaxedIKVars, om: &ObjectiveMaster, max_iter: usize, max_duration: Option<Duration>) -> Result<SolverStatus, SolverError> {
         let df = |u: &[f64], grad: &mut [f64]| -> Result<(), SolverError> {
             let (my_obj, my_grad) = om.gradient(u, v);
             for i in 0..my_grad.len() {
@@ -34,6 +38,9 @@ impl OptimizationEngineOpen {
         /* PROBLEM STATEMENT */
         let problem = Problem::new(&bounds, df, f);
         let mut panoc = PANOCOptimizer::new(problem, &mut self.cache).with_max_iter(max_iter).with_tolerance(0.0005);
+        if let Some(d) = max_duration {
+            panoc = panoc.with_max_duration(d);
+        }
         // let mut panoc = PANOCOptimizer::new(problem, &mut self.cache);
 
         // Invoke the solver
@@ -41,6 +48,7 @@ impl OptimizationEngineOpen {
 
         // println!("Panoc status: {:#?}", status);
         // println!("Panoc solution: {:#?}", x);
+        status
     }
 }
 
@@ -48,7 +56,7 @@ pub struct OptimizationEngineNLopt;
 impl OptimizationEngineNLopt {
     pub fn new() -> Self { OptimizationEngineNLopt{} }
 
-    pub fn optimize(&mut self, x_out: &mut [f64], v: &RelaxedIKVars, om: &ObjectiveMaster, max_iter: u32) {
+    pub fn optimize(&mut self, x_out: &mut [f64], v: &RelaxedIKVars, om: &ObjectiveMaster, max_iter: u32, max_duration: Option<Duration>) {
         let num_dim = v.robot.num_dof;
 
         let obj_f = |x: &[f64], _gradient: Option<&mut [f64]>, _params: &mut ()| -> f64 {
@@ -66,6 +74,10 @@ impl OptimizationEngineNLopt {
         let mut opt = Nlopt::new(Algorithm::Slsqp, num_dim, obj_f, Target::Minimize, ());
         opt.set_ftol_rel(0.000001);
         opt.set_maxeval(max_iter);
+        if let Some(d) = max_duration {
+            // nlopt reads a max time of 0 as no limit, so a spent budget still gets a tiny positive one
+            opt.set_maxtime(d.as_secs_f64().max(f64::EPSILON));
+        }
 
         // let mut x_init = x_out.to_vec();
         let res = opt.optimize(x_out);
@@ -8,13 +8,70 @@ use nalgebra::{Vector3, Isometry3, Point3};
 use ncollide3d::{shape, query, pipeline};
 use std::ops::Deref;
 use time::PreciseTime;
+use crate::spacetime::arm::yoshikawa_manipulability;
 
 pub fn groove_loss(x_val: f64, t: f64, d: i32, c: f64, f: f64, g: i32) -> f64 {
     -( (-(x_val - t).powi(d)) / (2.0 * c.powi(2) ) ).exp() + f * (x_val - t).powi(g)
 }
 
 pub fn groove_loss_derivative(x_val: f64, t: f64, d: i32, c: f64, f: f64, g: i32) -> f64 {
-    -( (-(x_val - t).powi(d)) / (2.0 * c.powi(2) ) ).exp() *  ((-d as f64 * (x_val - t)) /  (2.0 * c.powi(2))) + g as f64 * f * (x_val - t)
+    -( (-(x_val - t).powi(d)) / (2.0 * c.powi(2) ) ).exp() *  ((-d as f64 * (x_val - t).powi(d - 1)) /  (2.0 * c.powi(2))) + g as f64 * f * (x_val - t).powi(g - 1)
+}
+
+// the constants of groove_loss: a gaussian groove of degree d and width c around t, plus f * (x - t)^g so the
+// cost keeps growing away from the groove
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GrooveLossParams {
+    pub t: f64,
+    pub d: i32,
+    pub c: f64,
+    pub f: f64,
+    pub g: i32
+}
+impl GrooveLossParams {
+    pub fn new(t: f64, d: i32, c: f64, f: f64, g: i32) -> Self {Self{t, d, c, f, g}}
+
+    // pose matching and smoothness terms
+    pub fn tight() -> Self {GrooveLossParams::new(0.0, 2, 0.1, 10.0, 2)}
+
+    // collision and manipulability barriers
+    pub fn barrier() -> Self {GrooveLossParams::new(0.0, 2, 2.1, 0.0002, 4)}
+
+    pub fn loss(&self, x_val: f64) -> f64 {
+        groove_loss(x_val, self.t, self.d, self.c, self.f, self.g)
+    }
+
+    pub fn derivative(&self, x_val: f64) -> f64 {
+        groove_loss_derivative(x_val, self.t, self.d, self.c, self.f, self.g)
+    }
+
+    // (x_val, loss, derivative) at num_samples evenly spaced points from x_min to x_max, for plotting the curve while tuning
+    pub fn tabulate(&self, x_min: f64, x_max: f64, num_samples: usize) -> Vec<(f64, f64, f64)> {
+        let mut out: Vec<(f64, f64, f64)> = Vec::new();
+        for i in 0..num_samples {
+            let x_val = if num_samples > 1 { x_min + (x_max - x_min) * i as f64 / (num_samples - 1) as f64 } else { x_min };
+            out.push((x_val, self.loss(x_val), self.derivative(x_val)));
+        }
+        out
+    }
+}
+
+// groove loss over the norm of diff, where diff has an identity derivative w.r.t. x (used by the smoothness terms)
+fn norm_groove_loss_gradient(diff: &Vec<f64>, loss: &GrooveLossParams) -> (f64, Vec<f64>) {
+    let mut x_val = 0.0;
+    for i in 0..diff.len() {
+        x_val += diff[i].powi(2);
+    }
+    x_val = x_val.sqrt();
+
+    let mut grad: Vec<f64> = vec![0.0; diff.len()];
+    if x_val > 0.0 {
+        let g_prime = loss.derivative(x_val);
+        for i in 0..diff.len() {
+            grad[i] = g_prime * diff[i] / x_val;
+        }
+    }
+    (loss.loss(x_val), grad)
 }
 
 pub trait ObjectiveTrait {
@@ -49,96 +106,160 @@ pub trait ObjectiveTrait {
         (f_0, grad)
     }
     fn gradient_type(&self) -> usize {return 1}  // manual diff = 0, finite diff = 1
+    // objectives that shape their cost with groove_loss expose its parameters, so they can be tuned from config
+    fn groove_loss_params(&self) -> Option<GrooveLossParams> {None}
+    fn set_groove_loss_params(&mut self, params: GrooveLossParams) -> bool {false}
 }
 
 pub struct MatchEEPosGoals {
-    pub arm_idx: usize
+    pub arm_idx: usize,
+    pub loss: GrooveLossParams
 }
 impl MatchEEPosGoals {
-    pub fn new(arm_idx: usize) -> Self {Self{arm_idx}}
+    pub fn new(arm_idx: usize) -> Self {Self{arm_idx, loss: GrooveLossParams::tight()}}
 }
 impl ObjectiveTrait for MatchEEPosGoals {
     fn call(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> f64 {
-        let last_elem = frames[self.arm_idx].0.len() - 1;
-        let x_val = ( frames[self.arm_idx].0[last_elem] - v.goal_positions[self.arm_idx] ).norm();
+        let (ee_pos, _) = v.robot.arms[self.arm_idx].ee_pose_from_frames(&frames[self.arm_idx]);
+        let x_val = v.goal_masks[self.arm_idx].position_error(&v.goal_tolerances[self.arm_idx], &ee_pos, &v.goal_positions[self.arm_idx]);
 
-        groove_loss(x_val, 0., 2, 0.1, 10.0, 2)
+        self.loss.loss(x_val)
     }
 
     fn call_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> f64 {
-        let x_val = ( ee_poses[self.arm_idx].0 - v.goal_positions[self.arm_idx] ).norm();
-        groove_loss(x_val, 0., 2, 0.1, 10.0, 2)
+        let x_val = v.goal_masks[self.arm_idx].position_error(&v.goal_tolerances[self.arm_idx], &ee_poses[self.arm_idx].0, &v.goal_positions[self.arm_idx]);
+        self.loss.loss(x_val)
     }
+
+    fn gradient(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> (f64, Vec<f64>) {
+        let (ee_pos, _) = v.robot.arms[self.arm_idx].ee_pose_from_frames(&frames[self.arm_idx]);
+        let (x_val, dir) = v.goal_masks[self.arm_idx].position_error_gradient(&v.goal_tolerances[self.arm_idx], &ee_pos, &v.goal_positions[self.arm_idx]);
+
+        let mut grad: Vec<f64> = vec![0.0; x.len()];
+        if x_val > 0.0 {
+            // the error changes at dir . J_linear
+            let g_prime = self.loss.derivative(x_val);
+            let jacobian = v.robot.get_jacobian_immutable(frames, self.arm_idx);
+            for i in 0..x.len() {
+                let d = dir[0] * jacobian[(0, i)] + dir[1] * jacobian[(1, i)] + dir[2] * jacobian[(2, i)];
+                grad[i] = g_prime * d;
+            }
+        }
+        (self.loss.loss(x_val), grad)
+    }
+
+    fn gradient_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> (f64, Vec<f64>) {
+        let frames = v.robot.get_frames_immutable(x);
+        self.gradient(x, v, &frames)
+    }
+
+    fn gradient_type(&self) -> usize {return 0}
+
+    fn groove_loss_params(&self) -> Option<GrooveLossParams> {Some(self.loss)}
+    fn set_groove_loss_params(&mut self, params: GrooveLossParams) -> bool {self.loss = params; true}
 }
 
 pub struct MatchEEQuatGoals {
-    pub arm_idx: usize
+    pub arm_idx: usize,
+    pub loss: GrooveLossParams
 }
 impl MatchEEQuatGoals {
-    pub fn new(arm_idx: usize) -> Self {Self{arm_idx}}
+    pub fn new(arm_idx: usize) -> Self {Self{arm_idx, loss: GrooveLossParams::tight()}}
 }
 impl ObjectiveTrait for MatchEEQuatGoals {
     fn call(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> f64 {
-        let last_elem = frames[self.arm_idx].1.len() - 1;
-        let tmp = Quaternion::new(-frames[self.arm_idx].1[last_elem].w, -frames[self.arm_idx].1[last_elem].i, -frames[self.arm_idx].1[last_elem].j, -frames[self.arm_idx].1[last_elem].k);
-        let ee_quat2 = UnitQuaternion::from_quaternion(tmp);
+        let (_, ee_quat) = v.robot.arms[self.arm_idx].ee_pose_from_frames(&frames[self.arm_idx]);
+        let x_val = v.goal_masks[self.arm_idx].rotation_error(&v.goal_tolerances[self.arm_idx], &ee_quat, &v.goal_quats[self.arm_idx]);
 
-        let disp = angle_between(v.goal_quats[self.arm_idx], frames[self.arm_idx].1[last_elem]);
-        let disp2 = angle_between(v.goal_quats[self.arm_idx], ee_quat2);
-        let x_val = disp.min(disp2);
-
-        groove_loss(x_val, 0., 2, 0.1, 10.0, 2)
+        self.loss.loss(x_val)
     }
 
     fn call_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> f64 {
-        let tmp = Quaternion::new(-ee_poses[self.arm_idx].1.w, -ee_poses[self.arm_idx].1.i, -ee_poses[self.arm_idx].1.j, -ee_poses[self.arm_idx].1.k);
-        let ee_quat2 = UnitQuaternion::from_quaternion(tmp);
+        let x_val = v.goal_masks[self.arm_idx].rotation_error(&v.goal_tolerances[self.arm_idx], &ee_poses[self.arm_idx].1, &v.goal_quats[self.arm_idx]);
+        self.loss.loss(x_val)
+    }
+
+    fn gradient(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> (f64, Vec<f64>) {
+        let (_, ee_quat) = v.robot.arms[self.arm_idx].ee_pose_from_frames(&frames[self.arm_idx]);
+        let (x_val, dir) = v.goal_masks[self.arm_idx].rotation_error_gradient(&v.goal_tolerances[self.arm_idx], &ee_quat, &v.goal_quats[self.arm_idx]);
+
+        let mut grad: Vec<f64> = vec![0.0; x.len()];
+        if x_val > 0.0 {
+            // angular velocity of the end effector, per unit change of each joint, is the lower half of the jacobian.
+            // the error changes at dir . J_angular
+            let g_prime = self.loss.derivative(x_val);
+            let jacobian = v.robot.get_jacobian_immutable(frames, self.arm_idx);
+            for i in 0..x.len() {
+                let d = dir[0] * jacobian[(3, i)] + dir[1] * jacobian[(4, i)] + dir[2] * jacobian[(5, i)];
+                grad[i] = g_prime * d;
+            }
+        }
+        (self.loss.loss(x_val), grad)
+    }
 
-        let disp = angle_between(v.goal_quats[self.arm_idx], ee_poses[self.arm_idx].1);
-        let disp2 = angle_between(v.goal_quats[self.arm_idx], ee_quat2);
-        let x_val = disp.min(disp2);
-        groove_loss(x_val, 0., 2, 0.1, 10.0, 2)
+    fn gradient_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> (f64, Vec<f64>) {
+        let frames = v.robot.get_frames_immutable(x);
+        self.gradient(x, v, &frames)
     }
+
+    fn gradient_type(&self) -> usize {return 0}
+
+    fn groove_loss_params(&self) -> Option<GrooveLossParams> {Some(self.loss)}
+    fn set_groove_loss_params(&mut self, params: GrooveLossParams) -> bool {self.loss = params; true}
 }
 
-pub struct NNSelfCollision;
+// a solver built without a collision network skips self collision
+pub struct NNSelfCollision {
+    pub loss: GrooveLossParams
+}
+impl NNSelfCollision {
+    pub fn new() -> Self {Self{loss: GrooveLossParams::barrier()}}
+}
 impl ObjectiveTrait for NNSelfCollision {
     fn call(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> f64 {
-        let mut x_val = v.collision_nn.predict(&x.to_vec());
-        groove_loss(x_val, 0., 2, 2.1, 0.0002, 4)
+        let collision_nn = match &v.collision_nn { Some(nn) => nn, None => return 0.0 };
+        let mut x_val = collision_nn.predict(&x.to_vec());
+        self.loss.loss(x_val)
     }
 
     fn call_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> f64 {
-        let mut x_val = v.collision_nn.predict(&x.to_vec());
-        groove_loss(x_val, 0., 2, 2.1, 0.0002, 4)
+        let collision_nn = match &v.collision_nn { Some(nn) => nn, None => return 0.0 };
+        let mut x_val = collision_nn.predict(&x.to_vec());
+        self.loss.loss(x_val)
     }
 
     fn gradient(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> (f64, Vec<f64>) {
-        let (x_val, mut grad) = v.collision_nn.gradient(&x.to_vec());
-        let g_prime = groove_loss_derivative(x_val, 0., 2, 2.1, 0.0002, 4);
+        let collision_nn = match &v.collision_nn { Some(nn) => nn, None => return (0.0, vec![0.0; x.len()]) };
+        let (x_val, mut grad) = collision_nn.gradient(&x.to_vec());
+        let g_prime = self.loss.derivative(x_val);
         for i in 0..grad.len() {
             grad[i] *= g_prime;
         }
-        (x_val, grad)
+        (self.loss.loss(x_val), grad)
     }
 
     fn gradient_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> (f64, Vec<f64>) {
-        let (x_val, mut grad) = v.collision_nn.gradient(&x.to_vec());
-        let g_prime = groove_loss_derivative(x_val, 0., 2, 2.1, 0.0002, 4);
+        let collision_nn = match &v.collision_nn { Some(nn) => nn, None => return (0.0, vec![0.0; x.len()]) };
+        let (x_val, mut grad) = collision_nn.gradient(&x.to_vec());
+        let g_prime = self.loss.derivative(x_val);
         for i in 0..grad.len() {
             grad[i] *= g_prime;
         }
-        (x_val, grad)
+        (self.loss.loss(x_val), grad)
     }
 
     fn gradient_type(&self) -> usize {return 0}
+
+    fn groove_loss_params(&self) -> Option<GrooveLossParams> {Some(self.loss)}
+    fn set_groove_loss_params(&mut self, params: GrooveLossParams) -> bool {self.loss = params; true}
 }
 
 pub struct EnvCollision {
-    pub arm_idx: usize
+    pub arm_idx: usize,
+    pub loss: GrooveLossParams
 }
 impl EnvCollision {
-    pub fn new(arm_idx: usize) -> Self {Self{arm_idx}}
+    pub fn new(arm_idx: usize) -> Self {Self{arm_idx, loss: GrooveLossParams::new(0.0, 2, 3.5, 0.00005, 4)}}
 }
 impl ObjectiveTrait for EnvCollision {
     fn call(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> f64 {
@@ -169,47 +290,147 @@ impl ObjectiveTrait for EnvCollision {
         // let end = PreciseTime::now();
         // println!("Obstacles calculating takes {}", start.to(end));
 
-        groove_loss(x_val, 0., 2, 3.5, 0.00005, 4)
+        self.loss.loss(x_val)
     }
 
     fn call_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> f64 {
         let x_val = 1.0; // placeholder
-        groove_loss(x_val, 0., 2, 2.1, 0.0002, 4)
+        self.loss.loss(x_val)
     }
+
+    fn groove_loss_params(&self) -> Option<GrooveLossParams> {Some(self.loss)}
+    fn set_groove_loss_params(&mut self, params: GrooveLossParams) -> bool {self.loss = params; true}
 }
 
-pub struct JointLimits;
+// penalty_cutoff is how far out from the middle of its range (as a fraction of the half range) a joint can go before the cost picks up
+pub struct JointLimits {
+    pub penalty_cutoff: f64,
+    pub loss: GrooveLossParams
+}
+impl JointLimits {
+    pub fn new() -> Self {Self{penalty_cutoff: 0.9, loss: GrooveLossParams::new(0.0, 2, 0.32950, 0.1, 2)}}
+}
 impl ObjectiveTrait for JointLimits {
     fn call(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> f64 {
-        let mut sum = 0.0;
-        let penalty_cutoff: f64 = 0.9;
-        let a = 0.05 / (penalty_cutoff.powi(50));
-        for i in 0..v.robot.num_dof {
-            let l = v.robot.bounds[i][0];
-            let u = v.robot.bounds[i][1];
-            let r = (x[i] - l) / (u - l);
-            let n = 2.0 * (r - 0.5);
-            sum += a*n.powf(50.);
-        }
-        groove_loss(sum, 0.0, 2, 0.32950, 0.1, 2)
+        self.limits_gradient(x, v).0
     }
 
     fn call_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> f64 {
+        self.limits_gradient(x, v).0
+    }
+
+    fn gradient(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> (f64, Vec<f64>) {
+        self.limits_gradient(x, v)
+    }
+
+    fn gradient_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> (f64, Vec<f64>) {
+        self.limits_gradient(x, v)
+    }
+
+    fn gradient_type(&self) -> usize {return 0}
+
+    fn groove_loss_params(&self) -> Option<GrooveLossParams> {Some(self.loss)}
+    fn set_groove_loss_params(&mut self, params: GrooveLossParams) -> bool {self.loss = params; true}
+}
+impl JointLimits {
+    fn limits_gradient(&self, x: &[f64], v: &vars::RelaxedIKVars) -> (f64, Vec<f64>) {
         let mut sum = 0.0;
-        let penalty_cutoff: f64 = 0.85;
-        let a = 0.05 / (penalty_cutoff.powi(50));
+        let mut d_sum: Vec<f64> = vec![0.0; x.len()];
+        let a = 0.05 / (self.penalty_cutoff.powi(50));
         for i in 0..v.robot.num_dof {
             let l = v.robot.bounds[i][0];
             let u = v.robot.bounds[i][1];
             let r = (x[i] - l) / (u - l);
             let n = 2.0 * (r - 0.5);
             sum += a*n.powi(50);
+            d_sum[i] = a * 50.0 * n.powi(49) * 2.0 / (u - l);
+        }
+
+        let g_prime = self.loss.derivative(sum);
+        for i in 0..d_sum.len() {
+            d_sum[i] *= g_prime;
         }
-        groove_loss(sum, 0.0, 2, 0.32950, 0.1, 2)
+        (self.loss.loss(sum), d_sum)
+    }
+}
+
+pub struct MaximizeManipulability {
+    pub arm_idx: usize,
+    pub loss: GrooveLossParams
+}
+impl MaximizeManipulability {
+    pub fn new(arm_idx: usize) -> Self {Self{arm_idx, loss: GrooveLossParams::barrier()}}
+
+    // barrier on yoshikawa manipulability, close to flat above penalty_cutoff and growing quickly as it approaches 0
+    fn manipulability_loss(&self, manipulability: f64) -> f64 {
+        let penalty_cutoff: f64 = 0.01;
+        let x_val = (penalty_cutoff / (manipulability + 0.000001)).powi(2);
+        self.loss.loss(x_val)
+    }
+}
+impl ObjectiveTrait for MaximizeManipulability {
+    fn call(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> f64 {
+        let jacobian = v.robot.arms[self.arm_idx].get_jacobian_immutable(&frames[self.arm_idx]);
+        self.manipulability_loss(yoshikawa_manipulability(&jacobian))
+    }
+
+    fn call_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> f64 {
+        self.manipulability_loss(v.robot.manipulability(x, self.arm_idx))
+    }
+
+    fn groove_loss_params(&self) -> Option<GrooveLossParams> {Some(self.loss)}
+    fn set_groove_loss_params(&mut self, params: GrooveLossParams) -> bool {self.loss = params; true}
+}
+
+// keeps chain's end effector at a fixed pose relative to other_chain's, e.g. two hands holding one tray.
+// the pose comes from vars.relative_pose_goals, without one for this pair the objective is flat
+pub struct MatchRelativePose {
+    pub chain: usize,
+    pub other_chain: usize,
+    pub position_weight: f64,
+    pub rotation_weight: f64,
+    pub loss: GrooveLossParams
+}
+impl MatchRelativePose {
+    pub fn new(chain: usize, other_chain: usize, position_weight: f64, rotation_weight: f64) -> Self {
+        Self{chain, other_chain, position_weight, rotation_weight, loss: GrooveLossParams::tight()}
+    }
+
+    fn relative_pose_loss(&self, v: &vars::RelaxedIKVars, pos: &Vector3<f64>, quat: &UnitQuaternion<f64>,
+                          other_pos: &Vector3<f64>, other_quat: &UnitQuaternion<f64>) -> f64 {
+        let goal = match v.relative_pose_goal(self.chain, self.other_chain) {
+            Some(g) => g,
+            None => return self.loss.loss(0.0)
+        };
+        // where the goal puts chain's end effector, given where other_chain's is
+        let goal_pos = other_pos + other_quat * goal.pose.translation.vector;
+        let goal_quat = other_quat * goal.pose.rotation;
+        let x_val = self.position_weight * (pos - goal_pos).norm() + self.rotation_weight * goal_quat.angle_to(quat);
+        self.loss.loss(x_val)
+    }
+}
+impl ObjectiveTrait for MatchRelativePose {
+    fn call(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> f64 {
+        let (pos, quat) = v.robot.arms[self.chain].ee_pose_from_frames(&frames[self.chain]);
+        let (other_pos, other_quat) = v.robot.arms[self.other_chain].ee_pose_from_frames(&frames[self.other_chain]);
+        self.relative_pose_loss(v, &pos, &quat, &other_pos, &other_quat)
+    }
+
+    fn call_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> f64 {
+        self.relative_pose_loss(v, &ee_poses[self.chain].0, &ee_poses[self.chain].1,
+            &ee_poses[self.other_chain].0, &ee_poses[self.other_chain].1)
     }
+
+    fn groove_loss_params(&self) -> Option<GrooveLossParams> {Some(self.loss)}
+    fn set_groove_loss_params(&mut self, params: GrooveLossParams) -> bool {self.loss = params; true}
 }
 
-pub struct MinimizeVelocity;
+pub struct MinimizeVelocity {
+    pub loss: GrooveLossParams
+}
+impl MinimizeVelocity {
+    pub fn new() -> Self {Self{loss: GrooveLossParams::tight()}}
+}
 impl ObjectiveTrait for MinimizeVelocity {
     fn call(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> f64 {
         let mut x_val = 0.0;
@@ -217,7 +438,7 @@ impl ObjectiveTrait for MinimizeVelocity {
            x_val += (x[i] - v.xopt[i]).powi(2);
         }
         x_val = x_val.sqrt();
-        groove_loss(x_val, 0.0, 2, 0.1, 10.0, 2)
+        self.loss.loss(x_val)
     }
 
     fn call_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> f64 {
@@ -226,12 +447,38 @@ impl ObjectiveTrait for MinimizeVelocity {
            x_val += (x[i] - v.xopt[i]).powi(2);
         }
         x_val = x_val.sqrt();
-        groove_loss(x_val, 0.0, 2, 0.1, 10.0, 2)
+        self.loss.loss(x_val)
+    }
+
+    fn gradient(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> (f64, Vec<f64>) {
+        self.diff_gradient(x, v)
+    }
+
+    fn gradient_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> (f64, Vec<f64>) {
+        self.diff_gradient(x, v)
     }
 
+    fn gradient_type(&self) -> usize {return 0}
+
+    fn groove_loss_params(&self) -> Option<GrooveLossParams> {Some(self.loss)}
+    fn set_groove_loss_params(&mut self, params: GrooveLossParams) -> bool {self.loss = params; true}
+}
+impl MinimizeVelocity {
+    fn diff_gradient(&self, x: &[f64], v: &vars::RelaxedIKVars) -> (f64, Vec<f64>) {
+        let mut diff: Vec<f64> = Vec::new();
+        for i in 0..x.len() {
+            diff.push(x[i] - v.xopt[i]);
+        }
+        norm_groove_loss_gradient(&diff, &self.loss)
+    }
 }
 
-pub struct MinimizeAcceleration;
+pub struct MinimizeAcceleration {
+    pub loss: GrooveLossParams
+}
+impl MinimizeAcceleration {
+    pub fn new() -> Self {Self{loss: GrooveLossParams::tight()}}
+}
 impl ObjectiveTrait for MinimizeAcceleration {
     fn call(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> f64 {
         let mut x_val = 0.0;
@@ -241,7 +488,7 @@ impl ObjectiveTrait for MinimizeAcceleration {
             x_val += (v1 - v2).powi(2);
         }
         x_val = x_val.sqrt();
-        groove_loss(x_val, 0.0, 2, 0.1, 10.0, 2)
+        self.loss.loss(x_val)
     }
 
     fn call_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> f64 {
@@ -252,11 +499,40 @@ impl ObjectiveTrait for MinimizeAcceleration {
             x_val += (v1 - v2).powi(2);
         }
         x_val = x_val.sqrt();
-        groove_loss(x_val, 0.0, 2, 0.1, 10.0, 2)
+        self.loss.loss(x_val)
+    }
+
+    fn gradient(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> (f64, Vec<f64>) {
+        self.diff_gradient(x, v)
+    }
+
+    fn gradient_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> (f64, Vec<f64>) {
+        self.diff_gradient(x, v)
+    }
+
+    fn gradient_type(&self) -> usize {return 0}
+
+    fn groove_loss_params(&self) -> Option<GrooveLossParams> {Some(self.loss)}
+    fn set_groove_loss_params(&mut self, params: GrooveLossParams) -> bool {self.loss = params; true}
+}
+impl MinimizeAcceleration {
+    fn diff_gradient(&self, x: &[f64], v: &vars::RelaxedIKVars) -> (f64, Vec<f64>) {
+        let mut diff: Vec<f64> = Vec::new();
+        for i in 0..x.len() {
+            let v1 = x[i] - v.xopt[i];
+            let v2 = v.xopt[i] - v.prev_state[i];
+            diff.push(v1 - v2);
+        }
+        norm_groove_loss_gradient(&diff, &self.loss)
     }
 }
 
-pub struct MinimizeJerk;
+pub struct MinimizeJerk {
+    pub loss: GrooveLossParams
+}
+impl MinimizeJerk {
+    pub fn new() -> Self {Self{loss: GrooveLossParams::tight()}}
+}
 impl ObjectiveTrait for MinimizeJerk {
     fn call(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> f64 {
         let mut x_val = 0.0;
@@ -269,7 +545,7 @@ impl ObjectiveTrait for MinimizeJerk {
             x_val += (a1 - a2).powi(2);
         }
         x_val = x_val.sqrt();
-        groove_loss(x_val, 0.0, 2, 0.1, 10.0, 2)
+        self.loss.loss(x_val)
     }
 
     fn call_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> f64 {
@@ -283,6 +559,33 @@ impl ObjectiveTrait for MinimizeJerk {
             x_val += (a1 - a2).powi(2);
         }
         x_val = x_val.sqrt();
-        groove_loss(x_val, 0.0, 2, 0.1, 10.0, 2)
+        self.loss.loss(x_val)
+    }
+
+    fn gradient(&self, x: &[f64], v: &vars::RelaxedIKVars, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>) -> (f64, Vec<f64>) {
+        self.diff_gradient(x, v)
+    }
+
+    fn gradient_lite(&self, x: &[f64], v: &vars::RelaxedIKVars, ee_poses: &Vec<(nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>)>) -> (f64, Vec<f64>) {
+        self.diff_gradient(x, v)
+    }
+
+    fn gradient_type(&self) -> usize {return 0}
+
+    fn groove_loss_params(&self) -> Option<GrooveLossParams> {Some(self.loss)}
+    fn set_groove_loss_params(&mut self, params: GrooveLossParams) -> bool {self.loss = params; true}
+}
+impl MinimizeJerk {
+    fn diff_gradient(&self, x: &[f64], v: &vars::RelaxedIKVars) -> (f64, Vec<f64>) {
+        let mut diff: Vec<f64> = Vec::new();
+        for i in 0..x.len() {
+            let v1 = x[i] - v.xopt[i];
+            let v2 = v.xopt[i] - v.prev_state[i];
+            let v3 = v.prev_state[i] - v.prev_state2[i];
+            let a1 = v1 - v2;
+            let a2 = v2 - v3;
+            diff.push(a1 - a2);
+        }
+        norm_groove_loss_gradient(&diff, &self.loss)
     }
 }
\ No newline at end of file
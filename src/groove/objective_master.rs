@@ -1,76 +1,214 @@
 use crate::groove::objective::*;
 use crate::groove::vars::RelaxedIKVars;
+use crate::utils_rust::yaml_utils::ObjectiveSpec;
+use crate::utils_rust::error_utils::RelaxedIKError;
 
+// objectives are addressed by name, the presets name them <kind> or <kind>_<chain> (e.g. match_ee_quat_0, min_velocity)
 pub struct ObjectiveMaster {
     pub objectives: Vec<Box<dyn ObjectiveTrait + Send>>,
+    pub names: Vec<String>,
+    // the objective mode and ECAA go by kind and chain, so they also find objectives with a custom name
+    pub kinds: Vec<String>,
+    pub chains: Vec<Option<usize>>,
     pub num_chains: usize,
     pub weight_priors: Vec<f64>,
+    // the weights from the specs, set_weight or weight_priors, the objective mode only ever zeroes them in weight_priors
+    pub configured_weights: Vec<f64>,
+    pub objective_mode: String,
     pub lite: bool,
     pub finite_diff_grad: bool
 }
 
 impl ObjectiveMaster {
     pub fn standard_ik(num_chains: usize) -> Self {
-        let mut objectives: Vec<Box<dyn ObjectiveTrait + Send>> = Vec::new();
-        let mut weight_priors: Vec<f64> = Vec::new();
+        let mut om = Self{objectives: Vec::new(), names: Vec::new(), kinds: Vec::new(), chains: Vec::new(), num_chains, weight_priors: Vec::new(), configured_weights: Vec::new(), objective_mode: "ECA".to_string(), lite: true, finite_diff_grad: false};
         for i in 0..num_chains {
-            objectives.push(Box::new(MatchEEPosGoals::new(i)));
-            weight_priors.push(1.0);
-            objectives.push(Box::new(MatchEEQuatGoals::new(i)));
-            weight_priors.push(1.0);
+            om.push(format!("match_ee_pos_{}", i), "match_ee_pos", Some(i), Box::new(MatchEEPosGoals::new(i)), 1.0);
+            om.push(format!("match_ee_quat_{}", i), "match_ee_quat", Some(i), Box::new(MatchEEQuatGoals::new(i)), 1.0);
         }
-        Self{objectives, num_chains, weight_priors, lite: true, finite_diff_grad: true}
+        om
     }
 
     pub fn tune_weight_priors(&mut self, vars: &RelaxedIKVars) {
         let a = 0.05;
         let cap = 0.001;
-        for i in 0..self.num_chains {
+        for idx in 0..self.objectives.len() {
+            // match ee quat goal objectives
+            let i = match self.chains[idx] {
+                Some(i) if self.kinds[idx] == "match_ee_quat" => i,
+                _ => continue
+            };
             let mut score_max = 0.0;
             for (option, score) in &vars.env_collision.active_obstacles[i] {
                 if *score > score_max {
                     score_max = *score;
                 }
             }
-            // match ee quat goal objectives
-            let weight_cur = self.weight_priors[3*i+1];
-            let weight_delta = a / (a + score_max) - weight_cur;
+            let weight_cur = self.weight_priors[idx];
+            let weight_delta = self.configured_weights[idx] * a / (a + score_max) - weight_cur;
             if weight_delta.abs() < cap {
-                self.weight_priors[3*i+1] += weight_delta;
+                self.weight_priors[idx] += weight_delta;
             } else {
-                self.weight_priors[3*i+1] += cap * weight_delta / weight_delta.abs();
+                self.weight_priors[idx] += cap * weight_delta / weight_delta.abs();
             }
         }
     }
 
-    pub fn relaxed_ik(num_chains: usize, objective_mode: String) -> Self {
-        let mut objectives: Vec<Box<dyn ObjectiveTrait + Send>> = Vec::new();
-        let mut weight_priors: Vec<f64> = Vec::new();
+    pub fn relaxed_ik(num_chains: usize, objective_mode: String, avoid_singularities: bool) -> Self {
+        let mut om = Self{objectives: Vec::new(), names: Vec::new(), kinds: Vec::new(), chains: Vec::new(), num_chains, weight_priors: Vec::new(), configured_weights: Vec::new(), objective_mode: "ECA".to_string(), lite: false, finite_diff_grad: false};
         for i in 0..num_chains {
-            objectives.push(Box::new(MatchEEPosGoals::new(i)));
-            weight_priors.push(1.0);
-            objectives.push(Box::new(MatchEEQuatGoals::new(i)));
-            if objective_mode == "ECA3" {
-                weight_priors.push(0.0);
-            } else if objective_mode == "ECAA" {
-                weight_priors.push(1.0);
-            } else {
-                weight_priors.push(1.0);
+            om.push(format!("match_ee_pos_{}", i), "match_ee_pos", Some(i), Box::new(MatchEEPosGoals::new(i)), 1.0);
+            om.push(format!("match_ee_quat_{}", i), "match_ee_quat", Some(i), Box::new(MatchEEQuatGoals::new(i)), 1.0);
+            om.push(format!("env_collision_{}", i), "env_collision", Some(i), Box::new(EnvCollision::new(i)), 1.0);
+        }
+        om.push("min_velocity".to_string(), "min_velocity", None, Box::new(MinimizeVelocity::new()), 7.0);
+        om.push("min_acceleration".to_string(), "min_acceleration", None, Box::new(MinimizeAcceleration::new()), 2.0);
+        om.push("min_jerk".to_string(), "min_jerk", None, Box::new(MinimizeJerk::new()), 1.0);
+        om.push("joint_limits".to_string(), "joint_limits", None, Box::new(JointLimits::new()), 1.0);
+        om.push("self_collision".to_string(), "self_collision", None, Box::new(NNSelfCollision::new()), 1.0);
+        if avoid_singularities {
+            for i in 0..num_chains {
+                om.push(format!("maximize_manipulability_{}", i), "maximize_manipulability", Some(i), Box::new(MaximizeManipulability::new(i)), 1.0);
             }
-            objectives.push(Box::new(EnvCollision::new(i)));
-            if objective_mode == "noECA" {
-                weight_priors.push(0.0);
-            } else {
-                weight_priors.push(1.0);
+        }
+        om.apply_objective_mode(objective_mode.as_str());
+        om
+    }
+
+    // builds the stack described by an objectives list, see settings.yaml
+    pub fn from_specs(specs: &Vec<ObjectiveSpec>, num_chains: usize) -> Result<Self, RelaxedIKError> {
+        let mut om = Self{objectives: Vec::new(), names: Vec::new(), kinds: Vec::new(), chains: Vec::new(), num_chains, weight_priors: Vec::new(), configured_weights: Vec::new(), objective_mode: "ECA".to_string(), lite: false, finite_diff_grad: false};
+        for spec in specs {
+            if spec.kind == "match_relative_pose" {
+                let (chain, other_chain) = relative_pose_chains(spec, num_chains)?;
+                let objective = objective_from_spec(spec, chain)?;
+                let name = spec.name.clone().unwrap_or(format!("match_relative_pose_{}_{}", chain, other_chain));
+                om.add_objective(name.as_str(), spec.kind.as_str(), Some(chain), objective, spec.weight)?;
+                continue;
+            }
+            if spec.other_chain.is_some() {
+                return Err(RelaxedIKError::InvalidArgument{message: format!("objective kind {} doesn't take an other_chain", spec.kind)});
             }
+
+            let per_chain = ["match_ee_pos", "match_ee_quat", "env_collision", "maximize_manipulability"].contains(&spec.kind.as_str());
+            if !per_chain {
+                if spec.chain.is_some() {
+                    return Err(RelaxedIKError::InvalidArgument{message: format!("objective kind {} doesn't take a chain", spec.kind)});
+                }
+                let objective = objective_from_spec(spec, 0)?;
+                let name = spec.name.clone().unwrap_or(spec.kind.clone());
+                om.add_objective(name.as_str(), spec.kind.as_str(), None, objective, spec.weight)?;
+                continue;
+            }
+
+            let chains: Vec<usize> = match spec.chain {
+                Some(c) if c >= num_chains => return Err(RelaxedIKError::InvalidArgument{message: format!("objective {} is on chain {}, but the robot has {} chains", spec.kind, c, num_chains)}),
+                Some(c) => vec![c],
+                None => (0..num_chains).collect()
+            };
+            for c in chains {
+                let objective = objective_from_spec(spec, c)?;
+                let name = match (&spec.name, spec.chain) {
+                    (Some(n), Some(_)) => n.clone(),
+                    (Some(n), None) => format!("{}_{}", n, c),
+                    (None, _) => format!("{}_{}", spec.kind, c)
+                };
+                om.add_objective(name.as_str(), spec.kind.as_str(), Some(c), objective, spec.weight)?;
+            }
+        }
+        Ok(om)
+    }
+
+    // ECA3 zeroes the match_ee_quat weights and noECA the env_collision ones, every other weight goes back to its configured value
+    pub fn apply_objective_mode(&mut self, objective_mode: &str) {
+        self.objective_mode = objective_mode.to_string();
+        for idx in 0..self.objectives.len() {
+            self.weight_priors[idx] = self.mode_weight(idx);
+        }
+    }
+
+    // replaces every configured weight, in stack order
+    pub fn set_weights(&mut self, weights: Vec<f64>) -> Result<(), RelaxedIKError> {
+        if weights.len() != self.objectives.len() {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("got {} objective weights for {} objectives", weights.len(), self.objectives.len())});
+        }
+        self.configured_weights = weights;
+        for idx in 0..self.objectives.len() {
+            self.weight_priors[idx] = self.mode_weight(idx);
+        }
+        Ok(())
+    }
+
+    fn mode_weight(&self, idx: usize) -> f64 {
+        let zeroed = (self.kinds[idx] == "match_ee_quat" && self.objective_mode == "ECA3")
+            || (self.kinds[idx] == "env_collision" && self.objective_mode == "noECA");
+        if zeroed { 0.0 } else { self.configured_weights[idx] }
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    pub fn add_objective(&mut self, name: &str, kind: &str, chain: Option<usize>, objective: Box<dyn ObjectiveTrait + Send>, weight: f64) -> Result<(), RelaxedIKError> {
+        if self.index_of(name).is_some() {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("there is already an objective named {}", name)});
+        }
+        self.push(name.to_string(), kind, chain, objective, weight);
+        Ok(())
+    }
+
+    pub fn remove_objective(&mut self, name: &str) -> Result<(), RelaxedIKError> {
+        let idx = self.find(name)?;
+        self.objectives.remove(idx);
+        self.names.remove(idx);
+        self.kinds.remove(idx);
+        self.chains.remove(idx);
+        self.weight_priors.remove(idx);
+        self.configured_weights.remove(idx);
+        Ok(())
+    }
+
+    pub fn set_weight(&mut self, name: &str, weight: f64) -> Result<(), RelaxedIKError> {
+        let idx = self.find(name)?;
+        self.configured_weights[idx] = weight;
+        self.weight_priors[idx] = self.mode_weight(idx);
+        Ok(())
+    }
+
+    pub fn get_weight(&self, name: &str) -> Option<f64> {
+        self.index_of(name).map(|idx| self.weight_priors[idx])
+    }
+
+    pub fn get_groove_loss(&self, name: &str) -> Option<GrooveLossParams> {
+        match self.index_of(name) {
+            Some(idx) => self.objectives[idx].groove_loss_params(),
+            None => None
         }
-        objectives.push(Box::new(MinimizeVelocity));   weight_priors.push(7.0);
-        objectives.push(Box::new(MinimizeAcceleration));    weight_priors.push(2.0);
-        objectives.push(Box::new(MinimizeJerk));    weight_priors.push(1.0);
-        objectives.push(Box::new(JointLimits));    weight_priors.push(1.0);
-        objectives.push(Box::new(NNSelfCollision));    weight_priors.push(1.0);
+    }
 
-        Self{objectives, num_chains, weight_priors, lite: false, finite_diff_grad: true} // fix this
+    pub fn set_groove_loss(&mut self, name: &str, params: GrooveLossParams) -> Result<(), RelaxedIKError> {
+        let idx = self.find(name)?;
+        if !self.objectives[idx].set_groove_loss_params(params) {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("objective {} has no groove loss parameters", name)});
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, name: String, kind: &str, chain: Option<usize>, objective: Box<dyn ObjectiveTrait + Send>, weight: f64) {
+        self.objectives.push(objective);
+        self.names.push(name);
+        self.kinds.push(kind.to_string());
+        self.chains.push(chain);
+        self.configured_weights.push(weight);
+        let idx = self.objectives.len() - 1;
+        self.weight_priors.push(self.mode_weight(idx));
+    }
+
+    fn find(&self, name: &str) -> Result<usize, RelaxedIKError> {
+        match self.index_of(name) {
+            Some(idx) => Ok(idx),
+            None => Err(RelaxedIKError::InvalidArgument{message: format!("no objective named {}", name)})
+        }
     }
 
     pub fn call(&self, x: &[f64], vars: &RelaxedIKVars) -> f64 {
@@ -131,14 +269,15 @@ impl ObjectiveMaster {
         let mut f_0s: Vec<f64> = Vec::new();
         let frames_0 = vars.robot.get_frames_immutable(x);
         for i in 0..self.objectives.len() {
-            if self.objectives[i].gradient_type() == 1 {
+            if self.objectives[i].gradient_type() == 0 {
                 let (local_obj, local_grad) = self.objectives[i].gradient(x, vars, &frames_0);
                 f_0s.push(local_obj);
                 obj += self.weight_priors[i] * local_obj;
                 for j in 0..local_grad.len() {
                     grad[j] += self.weight_priors[i] * local_grad[j];
                 }
-            } else if self.objectives[i].gradient_type() == 0 {
+            } else if self.objectives[i].gradient_type() == 1 {
+                // objectives without a manual gradient share one forward kinematics pass per perturbed joint
                 finite_diff_list.push(i);
                 let local_obj = self.objectives[i].call(x, vars, &frames_0);
                 obj += self.weight_priors[i] * local_obj;
@@ -152,7 +291,7 @@ impl ObjectiveMaster {
                 x_h[i] += 0.0000001;
                 let frames_h = vars.robot.get_frames_immutable(x_h.as_slice());
                 for j in &finite_diff_list {
-                    let f_h = self.objectives[*j].call(x, vars, &frames_h);
+                    let f_h = self.objectives[*j].call(x_h.as_slice(), vars, &frames_h);
                     grad[i] += self.weight_priors[*j] * ((-f_0s[*j] + f_h) /  0.0000001);
                 }
             }
@@ -169,14 +308,15 @@ impl ObjectiveMaster {
         let mut f_0s: Vec<f64> = Vec::new();
         let poses_0 = vars.robot.get_ee_pos_and_quat_immutable(x);
         for i in 0..self.objectives.len() {
-            if self.objectives[i].gradient_type() == 1 {
+            if self.objectives[i].gradient_type() == 0 {
                 let (local_obj, local_grad) = self.objectives[i].gradient_lite(x, vars, &poses_0);
                 f_0s.push(local_obj);
                 obj += self.weight_priors[i] * local_obj;
                 for j in 0..local_grad.len() {
                     grad[j] += self.weight_priors[i] * local_grad[j];
                 }
-            } else if self.objectives[i].gradient_type() == 0 {
+            } else if self.objectives[i].gradient_type() == 1 {
+                // objectives without a manual gradient share one forward kinematics pass per perturbed joint
                 finite_diff_list.push(i);
                 let local_obj = self.objectives[i].call_lite(x, vars, &poses_0);
                 obj += self.weight_priors[i] * local_obj;
@@ -190,7 +330,7 @@ impl ObjectiveMaster {
                 x_h[i] += 0.0000001;
                 let poses_h = vars.robot.get_ee_pos_and_quat_immutable(x_h.as_slice());
                 for j in &finite_diff_list {
-                    let f_h = self.objectives[*j].call_lite(x, vars, &poses_h);
+                    let f_h = self.objectives[*j].call_lite(x_h.as_slice(), vars, &poses_h);
                     grad[i] += self.weight_priors[*j] * ((-f_0s[*j] + f_h) /  0.0000001);
                 }
             }
@@ -226,4 +366,118 @@ impl ObjectiveMaster {
 
         (f_0, grad)
     }
-}
\ No newline at end of file
+}
+
+fn objective_from_spec(spec: &ObjectiveSpec, chain: usize) -> Result<Box<dyn ObjectiveTrait + Send>, RelaxedIKError> {
+    let mut objective: Box<dyn ObjectiveTrait + Send> = match spec.kind.as_str() {
+        "match_ee_pos" => Box::new(MatchEEPosGoals::new(chain)),
+        "match_ee_quat" => Box::new(MatchEEQuatGoals::new(chain)),
+        "env_collision" => Box::new(EnvCollision::new(chain)),
+        "maximize_manipulability" => Box::new(MaximizeManipulability::new(chain)),
+        "min_velocity" => Box::new(MinimizeVelocity::new()),
+        "min_acceleration" => Box::new(MinimizeAcceleration::new()),
+        "min_jerk" => Box::new(MinimizeJerk::new()),
+        "joint_limits" => Box::new(JointLimits::new()),
+        "self_collision" => Box::new(NNSelfCollision::new()),
+        "match_relative_pose" => Box::new(MatchRelativePose::new(chain, spec.other_chain.unwrap_or(0),
+            spec.position_weight.unwrap_or(1.0), spec.rotation_weight.unwrap_or(1.0))),
+        _ => return Err(RelaxedIKError::InvalidArgument{message: format!("unknown objective kind {}", spec.kind)})
+    };
+    if let Some(params) = spec.groove_loss {
+        objective.set_groove_loss_params(params);
+    }
+    Ok(objective)
+}
+
+// match_relative_pose needs both chains, and they have to differ
+pub fn relative_pose_chains(spec: &ObjectiveSpec, num_chains: usize) -> Result<(usize, usize), RelaxedIKError> {
+    let (chain, other_chain) = match (spec.chain, spec.other_chain) {
+        (Some(c), Some(o)) => (c, o),
+        _ => return Err(RelaxedIKError::InvalidArgument{message: "objective kind match_relative_pose needs a chain and an other_chain".to_string()})
+    };
+    if chain >= num_chains || other_chain >= num_chains || chain == other_chain {
+        return Err(RelaxedIKError::InvalidArgument{message: format!("match_relative_pose needs two different chains out of {}, got {} and {}", num_chains, chain, other_chain)});
+    }
+    Ok((chain, other_chain))
+}
+#[cfg(test)]
+mod tests {
+    use crate::relaxed_ik::{RelaxedIK, RelaxedIKBuilder};
+    use crate::groove::collision_nn::CollisionNN;
+    use crate::spacetime::robot::Robot;
+    use crate::utils_rust::file_utils::ConfigPaths;
+    use crate::utils_rust::yaml_utils::InfoFileParser;
+    use crate::utils_rust::shape_parser_utils::SphereEnv;
+    use crate::utils_rust::subscriber_utils::{GoalMask, RotationMask};
+    use nalgebra::{Vector3, UnitQuaternion, Isometry3, Translation3};
+
+    // with a sphere next to the end effector and singularity avoidance, so the objectives without a manual gradient count too
+    fn solver(info_file: &str, mode: usize) -> RelaxedIK {
+        let paths = ConfigPaths::new(format!("{}/config/", env!("CARGO_MANIFEST_DIR")), String::new());
+        let ifp = InfoFileParser::from_yaml_path_with_paths(paths.info_file(info_file), &paths).unwrap();
+        let collision_nn = CollisionNN::from_yaml_path(paths.collision_nn(ifp.collision_nn_file.as_str())).unwrap();
+        let robot = Robot::from_info_file_parser(&ifp);
+        let ee = robot.get_ee_pos_and_quat_immutable(&ifp.starting_config)[0].0 + Vector3::new(0.15, 0.15, 0.15);
+        RelaxedIKBuilder::new(robot, ifp.starting_config.clone())
+            .collision_nn(collision_nn)
+            .add_sphere(SphereEnv::new("sphere".to_string(), 0.05, ee[0], ee[1], ee[2], false))
+            .avoid_singularities(true)
+            .mode(mode)
+            .build()
+            .unwrap()
+    }
+
+    // the manual gradients against a finite difference of the whole objective, at a few configs around the start,
+    // with goals away from the end effector so none of the terms sit at a kink
+    fn check_gradient(info_file: &str, mode: usize, tcp: Isometry3<f64>, mask: GoalMask) {
+        let mut r = solver(info_file, mode);
+        assert_eq!(r.om.lite, mode == 0);
+        r.set_tcp(0, tcp).unwrap();
+        r.set_goal_mask(0, mask).unwrap();
+        // the first update only queues the proximity events, the second makes the sphere active
+        r.vars.update_collision_world();
+        r.vars.update_collision_world();
+        assert!(r.vars.env_collision.active_obstacles[0].iter().any(|(h, _)| h.is_some()));
+        let start = r.vars.xopt.clone();
+        let ee_poses = r.vars.robot.get_ee_pos_and_quat_immutable(&start);
+        r.vars.goal_positions[0] = ee_poses[0].0 + Vector3::new(0.1, -0.05, 0.08);
+        r.vars.goal_quats[0] = UnitQuaternion::from_euler_angles(0.3, -0.2, 0.4) * ee_poses[0].1;
+        r.vars.prev_state = start.iter().map(|q| q - 0.02).collect();
+        r.vars.prev_state2 = start.iter().map(|q| q - 0.05).collect();
+
+        for k in 0..4 {
+            let x: Vec<f64> = (0..start.len()).map(|i| start[i] + 0.05 * (k as f64 + 1.0) * if i % 2 == 0 { 1.0 } else { -1.0 }).collect();
+            let (obj, grad) = r.om.gradient(&x, &r.vars);
+            let (obj_fd, grad_fd) = r.om.gradient_finite_diff(&x, &r.vars);
+            assert!((obj - obj_fd).abs() < 1e-9, "{} mode {} objective at config {}: {} vs {}", info_file, mode, k, obj, obj_fd);
+            for i in 0..x.len() {
+                assert!((grad[i] - grad_fd[i]).abs() < 1e-3 * grad_fd[i].abs().max(1.0),
+                    "{} mode {} joint {} at config {}: {} vs {}", info_file, mode, i, k, grad[i], grad_fd[i]);
+            }
+        }
+    }
+
+    fn tcp() -> Isometry3<f64> {
+        Isometry3::from_parts(Translation3::new(0.02, -0.01, 0.12), UnitQuaternion::from_euler_angles(0.1, 0.5, -0.3))
+    }
+
+    fn masked_goal() -> GoalMask {
+        GoalMask::new(Vector3::new(1.0, 0.5, 0.0), RotationMask::Axis(Vector3::new(0.0, 0.0, 1.0)))
+    }
+
+    #[test]
+    fn standard_ik_gradient_matches_finite_diff() {
+        for info_file in &["ur5_info.yaml", "panda_info.yaml"] {
+            check_gradient(info_file, 0, Isometry3::identity(), GoalMask::full());
+            check_gradient(info_file, 0, tcp(), masked_goal());
+        }
+    }
+
+    #[test]
+    fn relaxed_ik_gradient_matches_finite_diff() {
+        for info_file in &["ur5_info.yaml", "panda_info.yaml"] {
+            check_gradient(info_file, 1, Isometry3::identity(), GoalMask::full());
+            check_gradient(info_file, 1, tcp(), masked_goal());
+        }
+    }
+}
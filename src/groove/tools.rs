@@ -1,5 +1,6 @@
 use crate::spacetime::robot::{Robot};
 use crate::spacetime::arm::{Arm};
+use crate::utils_rust::error_utils::RelaxedIKError;
 
 #[derive(Clone, Debug)]
 pub struct RelaxedIKTools {
@@ -11,8 +12,8 @@ impl RelaxedIKTools {
         RelaxedIKTools{robot}
     }
 
-    pub fn from_yaml_path(fp: String) -> Self {
-        let robot = Robot::from_yaml_path(fp.clone());
-        RelaxedIKTools{robot}
+    pub fn from_yaml_path(fp: String) -> Result<Self, RelaxedIKError> {
+        let robot = Robot::from_yaml_path(fp.clone())?;
+        Ok(RelaxedIKTools{robot})
     }
 }
\ No newline at end of file
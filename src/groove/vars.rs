@@ -5,6 +5,9 @@ use crate::groove::collision_nn::CollisionNN;
 use crate::utils_rust::sampler::ThreadRobotSampler;
 use crate::utils_rust::file_utils::{*};
 use crate::groove::env_collision::{*};
+use crate::utils_rust::error_utils::RelaxedIKError;
+use crate::utils_rust::subscriber_utils::{GoalMask, GoalTolerance, GoalFrame, RelativePoseGoal};
+use crate::utils_rust::frame_utils::FrameTree;
 use ncollide3d::pipeline::{*};
 use ncollide3d::query::{*};
 use ncollide3d::shape::{*};
@@ -44,46 +47,73 @@ pub struct RelaxedIKVars {
     pub prev_state3: Vec<f64>,
     pub goal_positions: Vec<Vector3<f64>>,
     pub goal_quats: Vec<UnitQuaternion<f64>>,
+    pub goal_masks: Vec<GoalMask>,
+    pub goal_tolerances: Vec<GoalTolerance>,
+    // at most one per (chain, other_chain) pair, used by the match_relative_pose objectives
+    pub relative_pose_goals: Vec<RelativePoseGoal>,
     pub init_ee_positions: Vec<Vector3<f64>>,
     pub init_ee_quats: Vec<UnitQuaternion<f64>>,
-    pub position_mode_relative: bool, // if false, will be absolute
-    pub rotation_mode_relative: bool, // if false, will be absolute
-    pub collision_nn: CollisionNN,
+    pub position_mode: GoalFrame,
+    pub rotation_mode: GoalFrame,
+    pub collision_nn: Option<CollisionNN>,
     pub env_collision: RelaxedIKEnvCollision,
-    pub objective_mode: String
+    pub objective_mode: String,
+    pub avoid_singularities: bool,
+    // the frames goals can be given in, see EEPoseGoalsSubscriber::frames
+    pub frame_tree: FrameTree
 }
 impl RelaxedIKVars {
-    pub fn from_yaml_path(fp: String, position_mode_relative: bool, rotation_mode_relative: bool) -> Self {
-        let ifp = InfoFileParser::from_yaml_path(fp.clone());
-        let mut robot = Robot::from_yaml_path(fp.clone());
-        let num_chains = ifp.joint_names.len();
+    pub fn from_yaml_path(fp: String, position_mode: GoalFrame, rotation_mode: GoalFrame) -> Result<Self, RelaxedIKError> {
+        RelaxedIKVars::from_yaml_path_with_paths(fp, position_mode, rotation_mode, &ConfigPaths::from_env())
+    }
+
+    pub fn from_yaml_path_with_paths(fp: String, position_mode: GoalFrame, rotation_mode: GoalFrame, paths: &ConfigPaths) -> Result<Self, RelaxedIKError> {
+        let ifp = InfoFileParser::from_yaml_path_with_paths(fp.clone(), paths)?;
+        let robot = Robot::from_info_file_parser(&ifp);
+
+        let collision_nn_path = paths.collision_nn(ifp.collision_nn_file.as_str());
+        let collision_nn = CollisionNN::from_yaml_path(collision_nn_path)?;
+
+        let settings = SettingsParser::from_yaml_path_with_paths(paths.settings(), paths)?;
+
+        let mut vars = RelaxedIKVars::new(robot, ifp.starting_config.clone(), Some(collision_nn), settings.env_collision, position_mode, rotation_mode,
+            settings.objective_mode, settings.avoid_singularities)?;
+        vars.frame_tree = FrameTree::new(ifp.fixed_frame.clone(), ifp.ee_fixed_joints.clone());
+        Ok(vars)
+    }
+
+    // without a collision nn, self collision is not checked
+    pub fn new(robot: Robot, starting_config: Vec<f64>, collision_nn: Option<CollisionNN>, env_collision_file: EnvCollisionFileParser,
+               position_mode: GoalFrame, rotation_mode: GoalFrame, objective_mode: String, avoid_singularities: bool) -> Result<Self, RelaxedIKError> {
+        if starting_config.len() != robot.num_dof {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("got {} starting joint values for a robot with {} dof", starting_config.len(), robot.num_dof)});
+        }
+        let mut robot = robot;
+        let num_chains = robot.num_chains;
         let sampler = ThreadRobotSampler::new(robot.clone());
 
         let mut goal_positions: Vec<Vector3<f64>> = Vec::new();
         let mut goal_quats: Vec<UnitQuaternion<f64>> = Vec::new();
+        let mut goal_masks: Vec<GoalMask> = Vec::new();
+        let mut goal_tolerances: Vec<GoalTolerance> = Vec::new();
 
-        let init_ee_positions = robot.get_ee_positions(ifp.starting_config.as_slice());
-        let init_ee_quats = robot.get_ee_quats(ifp.starting_config.as_slice());
+        let init_ee_positions = robot.get_ee_positions(starting_config.as_slice());
+        let init_ee_quats = robot.get_ee_quats(starting_config.as_slice());
 
         for i in 0..num_chains {
             goal_positions.push(init_ee_positions[i]);
             goal_quats.push(init_ee_quats[i]);
+            goal_masks.push(GoalMask::full());
+            goal_tolerances.push(GoalTolerance::exact());
         }
 
-        let collision_nn_path = get_path_to_src()+ "relaxed_ik_core/config/collision_nn_rust/" + ifp.collision_nn_file.as_str() + ".yaml";
-        let collision_nn = CollisionNN::from_yaml_path(collision_nn_path);
-
-        let fp = get_path_to_src() + "relaxed_ik_core/config/settings.yaml";
-        let fp2 = fp.clone();
-        let env_collision_file = EnvCollisionFileParser::from_yaml_path(fp);
-        let frames = robot.get_frames_immutable(&ifp.starting_config.clone());
-        let env_collision = RelaxedIKEnvCollision::init_collision_world(env_collision_file, &frames);
-        let objective_mode = get_objective_mode(fp2);
+        let frames = robot.get_frames_immutable(&starting_config);
+        let env_collision = RelaxedIKEnvCollision::init_collision_world(env_collision_file, &frames)?;
 
-        RelaxedIKVars{robot, sampler, init_state: ifp.starting_config.clone(), xopt: ifp.starting_config.clone(),
-            prev_state: ifp.starting_config.clone(), prev_state2: ifp.starting_config.clone(), prev_state3: ifp.starting_config.clone(),
-            goal_positions, goal_quats, init_ee_positions, init_ee_quats, position_mode_relative, rotation_mode_relative, collision_nn, 
-            env_collision, objective_mode}
+        Ok(RelaxedIKVars{robot, sampler, init_state: starting_config.clone(), xopt: starting_config.clone(),
+            prev_state: starting_config.clone(), prev_state2: starting_config.clone(), prev_state3: starting_config.clone(),
+            goal_positions, goal_quats, goal_masks, goal_tolerances, relative_pose_goals: Vec::new(), init_ee_positions, init_ee_quats, position_mode, rotation_mode, collision_nn, 
+            env_collision, objective_mode, avoid_singularities, frame_tree: FrameTree::with_num_chains(num_chains)})
     }
 
     pub fn update(&mut self, xopt: Vec<f64>) {
@@ -93,12 +123,28 @@ impl RelaxedIKVars {
         self.xopt = xopt.clone();
     }
 
+    // forgets the motion history, as if the robot had been resting at x
+    pub fn reset(&mut self, x: Vec<f64>) {
+        self.prev_state3 = x.clone();
+        self.prev_state2 = x.clone();
+        self.prev_state = x.clone();
+        self.xopt = x;
+    }
+
+    // the goal for chain's end effector pose in other_chain's end effector frame, if there is one
+    pub fn relative_pose_goal(&self, chain: usize, other_chain: usize) -> Option<&RelativePoseGoal> {
+        self.relative_pose_goals.iter().find(|g| g.chain == chain && g.other_chain == other_chain)
+    }
+
     pub fn update_collision_world(&mut self) -> bool {
         let frames = self.robot.get_frames_immutable(&self.xopt);
         self.env_collision.update_links(&frames);
         for event in self.env_collision.world.proximity_events() {
-            let c1 = self.env_collision.world.objects.get(event.collider1).unwrap();
-            let c2 = self.env_collision.world.objects.get(event.collider2).unwrap();
+            // events are from the last update, so they can refer to an obstacle removed since then
+            let (c1, c2) = match (self.env_collision.world.objects.get(event.collider1), self.env_collision.world.objects.get(event.collider2)) {
+                (Some(c1), Some(c2)) => (c1, c2),
+                _ => continue
+            };
             if event.new_status == Proximity::Intersecting {
                 println!("===== {:?} Intersecting of {:?} =====", c1.data().name, c2.data().name);
             } else if event.new_status == Proximity::WithinMargin {
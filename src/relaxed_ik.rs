@@ -1,13 +1,22 @@
 use crate::groove::vars::RelaxedIKVars;
 use crate::groove::groove::{OptimizationEngineOpen, OptimizationEngineNLopt};
-use crate::groove::objective_master::ObjectiveMaster;
+use crate::groove::objective_master::{ObjectiveMaster, relative_pose_chains};
+use crate::groove::objective::MatchRelativePose;
+use crate::groove::collision_nn::CollisionNN;
+use crate::spacetime::robot::{Robot, ChainPoses};
 use crate::utils_rust::file_utils::{*};
-use crate::utils_rust::subscriber_utils::EEPoseGoalsSubscriber;
+use crate::utils_rust::subscriber_utils::{EEPoseGoalsSubscriber, GoalMask, GoalTolerance, GoalFrame, RelativePoseGoal};
 use crate::utils_rust::transformations::{*};
 use crate::utils_rust::yaml_utils::{*};
-use nalgebra::{Vector3, UnitQuaternion, Quaternion};
+use crate::utils_rust::shape_parser_utils::{CuboidEnv, SphereEnv, PCEnv, ObstacleAnimation};
+use crate::utils_rust::trajectory_utils::{CartesianTrajectory, JointTrajectory};
+use crate::utils_rust::frame_utils::FrameTree;
+use nalgebra::{Vector3, UnitQuaternion, Quaternion, Isometry3, Translation3};
 use crate::utils_rust::sampler::ThreadSampler;
+use crate::utils_rust::error_utils::RelaxedIKError;
 use std::os::raw::{c_double, c_int};
+use std::time::{Duration, Instant};
+use optimization_engine::core::ExitStatus;
 
 #[repr(C)]
 pub struct Opt {
@@ -15,6 +24,51 @@ pub struct Opt {
     pub length: c_int,
 }
 
+// for solve_precise_with_options. time_limit bounds the whole call, on top of max_restarts, and has to be positive
+#[derive(Clone, Debug)]
+pub struct PreciseSolveOptions {
+    // per chain, m
+    pub pos_tolerance: f64,
+    // per chain, rad
+    pub rot_tolerance: f64,
+    pub max_restarts: usize,
+    pub time_limit: Option<Duration>
+}
+impl PreciseSolveOptions {
+    pub fn new() -> Self {
+        Self{pos_tolerance: 0.005, rot_tolerance: 0.005, max_restarts: 100, time_limit: None}
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PreciseSolveResult {
+    // the first solution within the tolerances, or the closest one if found is false
+    pub x: Vec<f64>,
+    pub found: bool,
+    // 0 for the solve from the current configuration, n for the nth random restart
+    pub attempt: usize,
+    pub pos_errors: Vec<f64>,
+    pub rot_errors: Vec<f64>,
+    pub solve_time: Duration
+}
+
+#[derive(Clone, Debug)]
+pub struct SolveResult {
+    pub x: Vec<f64>,
+    // per chain, distance (m) and angle (rad) from the goal at x
+    pub pos_errors: Vec<f64>,
+    pub rot_errors: Vec<f64>,
+    pub objective_value: f64,
+    pub iterations: usize,
+    // wall time of the whole solve call
+    pub solve_time: Duration,
+    pub converged: bool,
+    // solve_with_deadline ran out of time, x is the best iterate found before the deadline
+    pub deadline_hit: bool,
+    // the robot was already within margin of an obstacle, so the previous solution was returned as is
+    pub skipped_due_to_collision: bool
+}
+
 pub struct RelaxedIK {
     pub vars: RelaxedIKVars,
     pub om: ObjectiveMaster,
@@ -22,62 +76,576 @@ pub struct RelaxedIK {
     pub groove_nlopt: OptimizationEngineNLopt
 }
 
-impl RelaxedIK {
-    pub fn from_info_file_name(info_file_name: String, mode: usize) -> Self {
-        let path_to_src = get_path_to_src();
-        let fp = path_to_src + "relaxed_ik_core/config/info_files/" + info_file_name.as_str();
-        RelaxedIK::from_yaml_path(fp.clone(), mode.clone())
+// builds a solver from an in-memory robot and scene, without reading any files.
+// the yaml loaders on RelaxedIK fill one of these in from the info file and settings.yaml
+pub struct RelaxedIKBuilder {
+    robot: Robot,
+    starting_config: Vec<f64>,
+    collision_nn: Option<CollisionNN>,
+    obstacles: EnvCollisionFileParser,
+    objective_mode: String,
+    avoid_singularities: bool,
+    objective_stack: Option<Vec<ObjectiveSpec>>,
+    weight_priors: Option<Vec<f64>>,
+    position_mode: GoalFrame,
+    rotation_mode: GoalFrame,
+    frame_names: Option<(String, Vec<String>)>,
+    mode: usize
+}
+
+impl RelaxedIKBuilder {
+    // defaults: no obstacles, the link radius and objective mode of the shipped settings.yaml, goals relative to the start pose, relaxed ik (mode 1)
+    pub fn new(robot: Robot, starting_config: Vec<f64>) -> Self {
+        let obstacles = EnvCollisionFileParser{robot_link_radius: 0.05, cuboids: Vec::new(), spheres: Vec::new(), pcds: Vec::new(), animations: Vec::new()};
+        Self{robot, starting_config, collision_nn: None, obstacles, objective_mode: "ECA".to_string(), avoid_singularities: false,
+            objective_stack: None, weight_priors: None, position_mode: GoalFrame::RelativeToStart, rotation_mode: GoalFrame::RelativeToStart, frame_names: None, mode: 1}
+    }
+
+    // without one, the self collision objective is skipped
+    pub fn collision_nn(mut self, collision_nn: CollisionNN) -> Self {
+        self.collision_nn = Some(collision_nn);
+        self
+    }
+
+    // replaces the whole scene, including the link radius
+    pub fn obstacles(mut self, obstacles: EnvCollisionFileParser) -> Self {
+        self.obstacles = obstacles;
+        self
+    }
+
+    pub fn link_radius(mut self, link_radius: f64) -> Self {
+        self.obstacles.robot_link_radius = link_radius;
+        self
+    }
+
+    pub fn add_cuboid(mut self, cuboid: CuboidEnv) -> Self {
+        self.obstacles.cuboids.push(cuboid);
+        self
+    }
+
+    pub fn add_sphere(mut self, sphere: SphereEnv) -> Self {
+        self.obstacles.spheres.push(sphere);
+        self
+    }
+
+    pub fn add_point_cloud(mut self, pcd: PCEnv) -> Self {
+        self.obstacles.pcds.push(pcd);
+        self
+    }
+
+    // the animated obstacle has to be added as dynamic
+    pub fn add_obstacle_animation(mut self, animation: ObstacleAnimation) -> Self {
+        self.obstacles.animations.push(animation);
+        self
+    }
+
+    // one of noECA, ECA, ECA3 or ECAA, see settings.yaml
+    pub fn objective_mode(mut self, objective_mode: String) -> Self {
+        self.objective_mode = objective_mode;
+        self
+    }
+
+    pub fn avoid_singularities(mut self, avoid_singularities: bool) -> Self {
+        self.avoid_singularities = avoid_singularities;
+        self
+    }
+
+    // replaces the preset objectives (relaxed ik, or standard ik for mode 0) with the given ones, see settings.yaml.
+    // the objective mode still picks the weights it controls, and ECAA still tunes them
+    pub fn objective_stack(mut self, objective_stack: Vec<ObjectiveSpec>) -> Self {
+        self.objective_stack = Some(objective_stack);
+        self
+    }
+
+    // overrides the weights the objective mode would pick, one per objective in ObjectiveMaster order
+    pub fn weight_priors(mut self, weight_priors: Vec<f64>) -> Self {
+        self.weight_priors = Some(weight_priors);
+        self
+    }
+
+    pub fn position_mode(mut self, position_mode: GoalFrame) -> Self {
+        self.position_mode = position_mode;
+        self
+    }
+
+    pub fn rotation_mode(mut self, rotation_mode: GoalFrame) -> Self {
+        self.rotation_mode = rotation_mode;
+        self
+    }
+
+    // true for RelativeToStart, false for Absolute
+    pub fn position_mode_relative(self, position_mode_relative: bool) -> Self {
+        self.position_mode(GoalFrame::from_relative(position_mode_relative))
+    }
+
+    pub fn rotation_mode_relative(self, rotation_mode_relative: bool) -> Self {
+        self.rotation_mode(GoalFrame::from_relative(rotation_mode_relative))
+    }
+
+    // names for the base frame and the end effector frames goals can refer to, ee_<chain index> and base work either way
+    pub fn frame_names(mut self, base_frame: String, ee_frames: Vec<String>) -> Self {
+        self.frame_names = Some((base_frame, ee_frames));
+        self
+    }
+
+    // 0 for standard ik, 1 for relaxed ik
+    pub fn mode(mut self, mode: usize) -> Self {
+        self.mode = mode;
+        self
     }
 
-    pub fn from_yaml_path(fp: String, mode: usize) -> Self {
-        let vars = RelaxedIKVars::from_yaml_path(fp.clone(), true, true);
-        let mut om = ObjectiveMaster::relaxed_ik(vars.robot.num_chains, vars.objective_mode.clone());
-        if mode == 0 {
+    pub fn build(self) -> Result<RelaxedIK, RelaxedIKError> {
+        check_objective_mode(self.objective_mode.as_str())?;
+        let mut vars = RelaxedIKVars::new(self.robot, self.starting_config, self.collision_nn, self.obstacles, self.position_mode,
+            self.rotation_mode, self.objective_mode, self.avoid_singularities)?;
+        if let Some((base_frame, ee_frames)) = self.frame_names {
+            if ee_frames.len() != vars.robot.num_chains {
+                return Err(RelaxedIKError::InvalidArgument{message: format!("got {} end effector frame names for {} chains", ee_frames.len(), vars.robot.num_chains)});
+            }
+            vars.frame_tree = FrameTree::new(base_frame, ee_frames);
+        }
+        let mut om = ObjectiveMaster::relaxed_ik(vars.robot.num_chains, vars.objective_mode.clone(), vars.avoid_singularities);
+        if let Some(objective_stack) = &self.objective_stack {
+            om = ObjectiveMaster::from_specs(objective_stack, vars.robot.num_chains)?;
+            om.apply_objective_mode(vars.objective_mode.as_str());
+            // relative pose objectives hold the pose the chains are in at the starting config
+            for spec in objective_stack {
+                if spec.kind == "match_relative_pose" {
+                    let (chain, other_chain) = relative_pose_chains(spec, vars.robot.num_chains)?;
+                    let pose = relative_ee_pose(&vars, chain, other_chain);
+                    vars.relative_pose_goals.push(RelativePoseGoal{chain, other_chain, pose});
+                }
+            }
+        } else if self.mode == 0 {
             om = ObjectiveMaster::standard_ik(vars.robot.num_chains);
         }
+        if let Some(weight_priors) = self.weight_priors {
+            om.set_weights(weight_priors)?;
+        }
 
         let groove = OptimizationEngineOpen::new(vars.robot.num_dof.clone());
         let groove_nlopt = OptimizationEngineNLopt::new();
 
-        Self{vars, om, groove, groove_nlopt}
+        Ok(RelaxedIK{vars, om, groove, groove_nlopt})
     }
+}
+
+// chain's end effector pose in other_chain's end effector frame, at the last solution
+fn relative_ee_pose(vars: &RelaxedIKVars, chain: usize, other_chain: usize) -> Isometry3<f64> {
+    let ee_poses = vars.robot.get_ee_pos_and_quat_immutable(&vars.xopt);
+    let pose = Isometry3::from_parts(Translation3::from(ee_poses[chain].0), ee_poses[chain].1);
+    let other_pose = Isometry3::from_parts(Translation3::from(ee_poses[other_chain].0), ee_poses[other_chain].1);
+    other_pose.inverse() * pose
+}
 
-    pub fn from_loaded(mode: usize) -> Self {
-        let path_to_src = get_path_to_src();
-        let fp1 = path_to_src +  "relaxed_ik_core/config/settings.yaml";
-        let info_file_name = get_info_file_name(fp1);
-        RelaxedIK::from_info_file_name(info_file_name.clone(), mode.clone())
+fn check_objective_mode(objective_mode: &str) -> Result<(), RelaxedIKError> {
+    if !["noECA", "ECA", "ECA3", "ECAA"].contains(&objective_mode) {
+        return Err(RelaxedIKError::InvalidArgument{message: format!("unknown objective mode {}, expected noECA, ECA, ECA3 or ECAA", objective_mode)});
     }
+    Ok(())
+}
 
-    pub fn solve(&mut self, ee_sub: &EEPoseGoalsSubscriber) -> Vec<f64> {
-        let mut out_x = self.vars.xopt.clone();
+impl RelaxedIK {
+    pub fn from_info_file_name(info_file_name: String, mode: usize) -> Result<Self, RelaxedIKError> {
+        let paths = ConfigPaths::from_env();
+        RelaxedIK::from_yaml_path_with_paths(paths.info_file(info_file_name.as_str()), mode, &paths)
+    }
+
+    pub fn from_yaml_path(fp: String, mode: usize) -> Result<Self, RelaxedIKError> {
+        RelaxedIK::from_yaml_path_with_paths(fp, mode, &ConfigPaths::from_env())
+    }
+
+    // fp is the info file, everything it refers to (urdf, collision nn, settings.yaml) is looked up through paths
+    pub fn from_yaml_path_with_paths(fp: String, mode: usize, paths: &ConfigPaths) -> Result<Self, RelaxedIKError> {
+        let settings = SettingsParser::from_yaml_path_with_paths(paths.settings(), paths)?;
+        RelaxedIK::from_settings(fp, mode, paths, &settings)
+    }
 
-        if self.vars.rotation_mode_relative {
-            for i in 0..self.vars.robot.num_chains {
-                self.vars.goal_positions[i] = self.vars.init_ee_positions[i] + ee_sub.pos_goals[i];
-                self.vars.goal_quats[i] = ee_sub.quat_goals[i] * self.vars.init_ee_quats[i];
+    // same as from_yaml_path_with_paths, with settings.yaml already parsed. its loaded_robot.name is not used, fp is
+    pub fn from_settings(fp: String, mode: usize, paths: &ConfigPaths, settings: &SettingsParser) -> Result<Self, RelaxedIKError> {
+        let ifp = InfoFileParser::from_yaml_path_with_paths(fp.clone(), paths)?;
+        let collision_nn = CollisionNN::from_yaml_path(paths.collision_nn(ifp.collision_nn_file.as_str()))?;
+
+        let mut builder = RelaxedIKBuilder::new(Robot::from_info_file_parser(&ifp), ifp.starting_config.clone())
+            .collision_nn(collision_nn)
+            .obstacles(settings.env_collision.clone())
+            .objective_mode(settings.objective_mode.clone())
+            .avoid_singularities(settings.avoid_singularities)
+            .position_mode(settings.position_mode)
+            .rotation_mode(settings.rotation_mode)
+            .frame_names(ifp.fixed_frame.clone(), ifp.ee_fixed_joints.clone())
+            .mode(mode);
+        // an objectives list only replaces the relaxed ik preset, standard ik (mode 0) stays as it is
+        if mode != 0 {
+            if let Some(objective_stack) = settings.objective_stack.clone() {
+                builder = builder.objective_stack(objective_stack);
             }
-        } else {
-            for i in 0..self.vars.robot.num_chains  {
-                self.vars.goal_positions[i] = ee_sub.pos_goals[i].clone();
-                self.vars.goal_quats[i] = ee_sub.quat_goals[i].clone();
+        }
+        builder.build()
+    }
+
+    pub fn from_loaded(mode: usize) -> Result<Self, RelaxedIKError> {
+        RelaxedIK::from_config_paths(&ConfigPaths::from_env(), mode)
+    }
+
+    // loads the robot named in paths.settings()
+    pub fn from_config_paths(paths: &ConfigPaths, mode: usize) -> Result<Self, RelaxedIKError> {
+        let settings = SettingsParser::from_yaml_path_with_paths(paths.settings(), paths)?;
+        RelaxedIK::from_settings(paths.info_file(settings.info_file_name.as_str()), mode, paths, &settings)
+    }
+
+    pub fn reset(&mut self, x: Vec<f64>) -> Result<(), RelaxedIKError> {
+        if x.len() != self.vars.robot.num_dof {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("got {} joint values for a robot with {} dof", x.len(), self.vars.robot.num_dof)});
+        }
+        self.vars.reset(x);
+        self.vars.update_collision_world();
+        Ok(())
+    }
+
+    // moves the animated obstacles to time t (seconds) of their animations. they take effect from the next solve
+    pub fn set_animation_time(&mut self, t: f64) {
+        self.vars.env_collision.set_animation_time(t);
+    }
+
+    // one of noECA, ECA, ECA3 or ECAA, see settings.yaml
+    pub fn set_objective_mode(&mut self, objective_mode: &str) -> Result<(), RelaxedIKError> {
+        check_objective_mode(objective_mode)?;
+        self.vars.objective_mode = objective_mode.to_string();
+        // standard ik (mode 0) doesn't depend on the objective mode
+        if !self.om.lite {
+            self.om.apply_objective_mode(objective_mode);
+        }
+        Ok(())
+    }
+
+    // used by every later solve that doesn't set its own frames through EEPoseGoalsSubscriber
+    pub fn set_goal_frames(&mut self, position_mode: GoalFrame, rotation_mode: GoalFrame) {
+        self.vars.position_mode = position_mode;
+        self.vars.rotation_mode = rotation_mode;
+    }
+
+    fn set_goals(&mut self, ee_sub: &EEPoseGoalsSubscriber) -> Result<(), RelaxedIKError> {
+        let num_chains = self.vars.robot.num_chains;
+        if ee_sub.pos_goals.len() < num_chains || ee_sub.quat_goals.len() < num_chains {
+            return Err(RelaxedIKError::InvalidGoals{message: format!("got {} position and {} rotation goals for {} chains",
+                ee_sub.pos_goals.len(), ee_sub.quat_goals.len(), num_chains)});
+        }
+
+        if ee_sub.frames.len() > 0 && ee_sub.frames.len() != num_chains {
+            return Err(RelaxedIKError::InvalidGoals{message: format!("got {} goal frames for {} chains", ee_sub.frames.len(), num_chains)});
+        }
+
+        if ee_sub.masks.len() > 0 {
+            if ee_sub.masks.len() != num_chains {
+                return Err(RelaxedIKError::InvalidGoals{message: format!("got {} goal masks for {} chains", ee_sub.masks.len(), num_chains)});
+            }
+            for i in 0..num_chains {
+                self.set_goal_mask(i, ee_sub.masks[i])?;
+            }
+        }
+
+        if ee_sub.tolerances.len() > 0 {
+            if ee_sub.tolerances.len() != num_chains {
+                return Err(RelaxedIKError::InvalidGoals{message: format!("got {} goal tolerances for {} chains", ee_sub.tolerances.len(), num_chains)});
+            }
+            for i in 0..num_chains {
+                self.set_goal_tolerance(i, ee_sub.tolerances[i])?;
+            }
+        }
+
+        let position_mode = ee_sub.position_mode.unwrap_or(self.vars.position_mode);
+        let rotation_mode = ee_sub.rotation_mode.unwrap_or(self.vars.rotation_mode);
+        let current_ee_poses = self.vars.robot.get_ee_pos_and_quat_immutable(&self.vars.xopt);
+        let mut goal_positions = self.vars.goal_positions.clone();
+        let mut goal_quats = self.vars.goal_quats.clone();
+        let mut resolved = vec![false; num_chains];
+        for i in 0..num_chains {
+            if ee_sub.frames.len() > 0 && !self.vars.frame_tree.is_base(ee_sub.frames[i].as_str()) {
+                continue;
+            }
+            let (current_pos, current_quat) = current_ee_poses[i];
+            goal_positions[i] = match position_mode {
+                GoalFrame::Absolute => ee_sub.pos_goals[i],
+                GoalFrame::RelativeToStart => self.vars.init_ee_positions[i] + ee_sub.pos_goals[i],
+                GoalFrame::RelativeToCurrent => current_pos + ee_sub.pos_goals[i],
+                GoalFrame::RelativeToTool => current_pos + current_quat * ee_sub.pos_goals[i]
+            };
+            goal_quats[i] = match rotation_mode {
+                GoalFrame::Absolute => ee_sub.quat_goals[i],
+                GoalFrame::RelativeToStart => ee_sub.quat_goals[i] * self.vars.init_ee_quats[i],
+                GoalFrame::RelativeToCurrent => ee_sub.quat_goals[i] * current_quat,
+                GoalFrame::RelativeToTool => current_quat * ee_sub.quat_goals[i]
+            };
+            resolved[i] = true;
+        }
+
+        // a goal can be given in another chain's end effector frame, which is that chain's goal pose,
+        // so keep going over the rest until every frame it depends on is resolved
+        let mut num_resolved = resolved.iter().filter(|r| **r).count();
+        while num_resolved < num_chains {
+            let num_resolved_before = num_resolved;
+            for i in 0..num_chains {
+                if resolved[i] {
+                    continue;
+                }
+                let frame = ee_sub.frames[i].as_str();
+                let frame_pose = self.frame_pose_with(frame, |c| {
+                    if resolved[c] { Some(Isometry3::from_parts(Translation3::from(goal_positions[c]), goal_quats[c])) } else { None }
+                });
+                match frame_pose {
+                    Some(Some(pose)) => {
+                        goal_positions[i] = pose.translation.vector + pose.rotation * ee_sub.pos_goals[i];
+                        goal_quats[i] = pose.rotation * ee_sub.quat_goals[i];
+                        resolved[i] = true;
+                        num_resolved += 1;
+                    },
+                    Some(None) => {},
+                    None => return Err(RelaxedIKError::InvalidGoals{message: format!("unknown frame {} for the goal of chain {}", frame, i)})
+                }
+            }
+            if num_resolved == num_resolved_before {
+                let unresolved: Vec<usize> = (0..num_chains).filter(|i| !resolved[*i]).collect();
+                return Err(RelaxedIKError::InvalidGoals{message: format!("the goal frames of chains {:?} depend on each other", unresolved)});
             }
         }
 
+        self.vars.goal_positions = goal_positions;
+        self.vars.goal_quats = goal_quats;
+        Ok(())
+    }
+
+    // the pose of frame in the robot's base frame, None if it doesn't exist, Some(None) if it hangs off an end
+    // effector chain_pose has no pose for yet
+    fn frame_pose_with<F: Fn(usize) -> Option<Isometry3<f64>>>(&self, frame: &str, chain_pose: F) -> Option<Option<Isometry3<f64>>> {
+        let (root, offset) = self.vars.frame_tree.root(frame);
+        let root_pose = if let Some(pose) = self.vars.frame_tree.fixed_pose_in_base(root.as_str()) {
+            pose
+        } else if let Some(c) = self.vars.frame_tree.chain_of(root.as_str()) {
+            match chain_pose(c) {
+                Some(pose) => pose,
+                None => return Some(None)
+            }
+        } else {
+            self.vars.env_collision.obstacle_pose(root.as_str())?
+        };
+        Some(Some(root_pose * offset))
+    }
+
+    // the current pose of frame in the robot's base frame, end effector frames are at the last solution
+    pub fn frame_pose(&self, frame: &str) -> Result<Isometry3<f64>, RelaxedIKError> {
+        let ee_poses = self.vars.robot.get_ee_pos_and_quat_immutable(&self.vars.xopt);
+        match self.frame_pose_with(frame, |c| Some(Isometry3::from_parts(Translation3::from(ee_poses[c].0), ee_poses[c].1))) {
+            Some(Some(pose)) => Ok(pose),
+            _ => Err(RelaxedIKError::InvalidArgument{message: format!("unknown frame {}", frame)})
+        }
+    }
+
+    // a fixed frame goals can be given in, see FrameTree::add_frame. it can't share a name with an obstacle
+    pub fn add_frame(&mut self, name: &str, parent: &str, transform: Isometry3<f64>) -> Result<(), RelaxedIKError> {
+        if self.vars.env_collision.obstacle_pose(name).is_some() {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("an obstacle named {} already exists", name)});
+        }
+        self.vars.frame_tree.add_frame(name, parent, transform)
+    }
+
+    // swaps the tool on a chain: tcp is the tool center point in the frame of the chain's last joint. goals, errors and
+    // forward kinematics all refer to it from the next call, and relative goals are measured from the new tool's pose
+    // at the starting config. relative pose goals are kept as they are
+    pub fn set_tcp(&mut self, chain: usize, tcp: Isometry3<f64>) -> Result<(), RelaxedIKError> {
+        if chain >= self.vars.robot.num_chains {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("tcp for chain {}, but the robot has {} chains", chain, self.vars.robot.num_chains)});
+        }
+        self.vars.robot.arms[chain].tcp = tcp;
+        let init_ee_poses = self.vars.robot.get_ee_pos_and_quat_immutable(&self.vars.init_state);
+        self.vars.init_ee_positions[chain] = init_ee_poses[chain].0;
+        self.vars.init_ee_quats[chain] = init_ee_poses[chain].1;
+        Ok(())
+    }
+
+    pub fn get_tcp(&self, chain: usize) -> Option<Isometry3<f64>> {
+        self.vars.robot.arms.get(chain).map(|a| a.tcp)
+    }
+
+    fn check_num_joints(&self, x: &[f64]) -> Result<(), RelaxedIKError> {
+        if x.len() != self.vars.robot.num_dof {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("got {} joint values for a robot with {} dof", x.len(), self.vars.robot.num_dof)});
+        }
+        Ok(())
+    }
+
+    // forward kinematics at x, which doesn't need to be a solution: per chain, the pose in the robot's base frame of
+    // the link each joint moves, the end effector link and its tcp. for drawing the robot or checking a config
+    pub fn link_poses(&self, x: &[f64]) -> Result<Vec<ChainPoses>, RelaxedIKError> {
+        self.check_num_joints(x)?;
+        self.vars.robot.link_poses(x)
+    }
+
+    pub fn link_poses_batch(&self, xs: &[Vec<f64>]) -> Result<Vec<Vec<ChainPoses>>, RelaxedIKError> {
+        for i in 0..xs.len() {
+            self.check_num_joints(xs[i].as_slice())?;
+        }
+        self.vars.robot.link_poses_batch(xs)
+    }
+
+    // name is a joint name, for the link it moves, or an end effector frame name, for its tcp
+    pub fn link_pose(&self, x: &[f64], name: &str) -> Result<Isometry3<f64>, RelaxedIKError> {
+        self.check_num_joints(x)?;
+        if let Some(pose) = self.vars.robot.link_pose(x, name)? {
+            return Ok(pose);
+        }
+        match self.vars.frame_tree.chain_of(name) {
+            Some(c) => Ok(self.vars.robot.link_poses(x)?[c].tcp),
+            None => Err(RelaxedIKError::InvalidArgument{message: format!("no joint or end effector named {}", name)})
+        }
+    }
+
+    // pose is chain's end effector pose in other_chain's end effector frame, it replaces any earlier one for the pair
+    pub fn set_relative_pose_goal(&mut self, chain: usize, other_chain: usize, pose: Isometry3<f64>) -> Result<(), RelaxedIKError> {
+        let num_chains = self.vars.robot.num_chains;
+        if chain >= num_chains || other_chain >= num_chains || chain == other_chain {
+            return Err(RelaxedIKError::InvalidGoals{message: format!("relative pose goal needs two different chains out of {}, got {} and {}", num_chains, chain, other_chain)});
+        }
+        self.vars.relative_pose_goals.retain(|g| !(g.chain == chain && g.other_chain == other_chain));
+        self.vars.relative_pose_goals.push(RelativePoseGoal{chain, other_chain, pose});
+        Ok(())
+    }
+
+    // holds the chains at their relative pose in the last solution, e.g. once both hands have grasped an object
+    pub fn hold_relative_pose(&mut self, chain: usize, other_chain: usize) -> Result<(), RelaxedIKError> {
+        if chain >= self.vars.robot.num_chains || other_chain >= self.vars.robot.num_chains {
+            return Err(RelaxedIKError::InvalidGoals{message: format!("relative pose goal for chains {} and {}, but the robot has {} chains", chain, other_chain, self.vars.robot.num_chains)});
+        }
+        let pose = relative_ee_pose(&self.vars, chain, other_chain);
+        self.set_relative_pose_goal(chain, other_chain, pose)
+    }
+
+    // the match_relative_pose objective for the pair goes flat until a new goal is set
+    pub fn clear_relative_pose_goal(&mut self, chain: usize, other_chain: usize) {
+        self.vars.relative_pose_goals.retain(|g| !(g.chain == chain && g.other_chain == other_chain));
+    }
+
+    // adds a match_relative_pose_<chain>_<other_chain> objective holding the chains' relative pose in the last solution
+    pub fn add_relative_pose_objective(&mut self, chain: usize, other_chain: usize, weight: f64, position_weight: f64, rotation_weight: f64) -> Result<(), RelaxedIKError> {
+        self.hold_relative_pose(chain, other_chain)?;
+        let name = format!("match_relative_pose_{}_{}", chain, other_chain);
+        self.om.add_objective(name.as_str(), "match_relative_pose", Some(chain), Box::new(MatchRelativePose::new(chain, other_chain, position_weight, rotation_weight)), weight)
+    }
+
+    // stays in place for later solves, until replaced here or through EEPoseGoalsSubscriber::masks
+    pub fn set_goal_mask(&mut self, chain: usize, mask: GoalMask) -> Result<(), RelaxedIKError> {
+        if chain >= self.vars.robot.num_chains {
+            return Err(RelaxedIKError::InvalidGoals{message: format!("goal mask for chain {}, but the robot has {} chains", chain, self.vars.robot.num_chains)});
+        }
+        if !mask.is_valid() {
+            return Err(RelaxedIKError::InvalidGoals{message: format!("goal mask for chain {} needs non-negative position weights and a non-zero rotation axis, got {:?}", chain, mask)});
+        }
+        self.vars.goal_masks[chain] = mask;
+        Ok(())
+    }
+
+    // any pose inside the band costs the same as hitting the goal exactly. stays in place like set_goal_mask
+    pub fn set_goal_tolerance(&mut self, chain: usize, tolerance: GoalTolerance) -> Result<(), RelaxedIKError> {
+        if chain >= self.vars.robot.num_chains {
+            return Err(RelaxedIKError::InvalidGoals{message: format!("goal tolerance for chain {}, but the robot has {} chains", chain, self.vars.robot.num_chains)});
+        }
+        if !tolerance.is_valid() {
+            return Err(RelaxedIKError::InvalidGoals{message: format!("goal tolerance for chain {} needs finite, non-negative bounds, got {:?}", chain, tolerance)});
+        }
+        self.vars.goal_tolerances[chain] = tolerance;
+        Ok(())
+    }
+
+    // distance of each end effector at x from its current goal, (position errors, rotation errors), ignoring what the goal masks leave free.
+    // with a goal tolerance, this is the distance outside the band, 0 inside it
+    pub fn get_ee_errors(&self, x: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let mut pos_errors: Vec<f64> = Vec::new();
+        let mut rot_errors: Vec<f64> = Vec::new();
+        let ee_poses = self.vars.robot.get_ee_pos_and_quat_immutable(x);
+        for i in 0..self.vars.robot.num_chains {
+            pos_errors.push( self.vars.goal_masks[i].position_error(&self.vars.goal_tolerances[i], &ee_poses[i].0, &self.vars.goal_positions[i]) );
+            rot_errors.push( self.vars.goal_masks[i].rotation_error(&self.vars.goal_tolerances[i], &ee_poses[i].1, &self.vars.goal_quats[i]) );
+        }
+        (pos_errors, rot_errors)
+    }
+
+    pub fn solve(&mut self, ee_sub: &EEPoseGoalsSubscriber) -> Result<SolveResult, RelaxedIKError> {
+        self.solve_until(ee_sub, None)
+    }
+
+    // for fixed rate control loops: stops optimizing at deadline and returns the iterate reached by then, with deadline_hit set.
+    // the deadline is checked between optimizer iterations, so leave some slack. one that has already passed returns the previous solution
+    pub fn solve_with_deadline(&mut self, ee_sub: &EEPoseGoalsSubscriber, deadline: Instant) -> Result<SolveResult, RelaxedIKError> {
+        self.solve_until(ee_sub, Some(deadline))
+    }
+
+    fn solve_until(&mut self, ee_sub: &EEPoseGoalsSubscriber, deadline: Option<Instant>) -> Result<SolveResult, RelaxedIKError> {
+        let start = Instant::now();
+        let mut out_x = self.vars.xopt.clone();
+
+        self.set_goals(ee_sub)?;
+
+        let mut iterations = 0;
+        let mut converged = false;
+        let mut deadline_hit = false;
+        let objective_value;
         let in_collision = self.vars.update_collision_world();
-        if !in_collision {
+        let time_left = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        if !in_collision && time_left != Some(Duration::from_secs(0)) {
             if self.vars.objective_mode == "ECAA" {
                 self.om.tune_weight_priors(&self.vars);
             }
-            self.groove.optimize(&mut out_x, &self.vars, &self.om, 100);
+            let status = self.groove.optimize(&mut out_x, &self.vars, &self.om, 100, time_left)
+                .map_err(|e| RelaxedIKError::Solver{message: format!("{:?}", e)})?;
+            iterations = status.iterations();
+            converged = status.has_converged();
+            deadline_hit = status.exit_status() == ExitStatus::NotConvergedOutOfTime;
+            objective_value = status.cost_value();
             self.vars.update(out_x.clone());  
-        }  
-        out_x
+        } else {
+            // out of time before the optimizer could start
+            if !in_collision {
+                deadline_hit = true;
+            }
+            objective_value = self.om.call(&out_x, &self.vars);
+        }
+
+        let (pos_errors, rot_errors) = self.get_ee_errors(&out_x);
+        Ok(SolveResult{x: out_x, pos_errors, rot_errors, objective_value, iterations, solve_time: start.elapsed(), converged,
+            deadline_hit, skipped_due_to_collision: in_collision})
+    }
+
+    // solves for the trajectory's goals every 1 / rate seconds of trajectory time, from its first waypoint to its last.
+    // each solve starts from the previous one, like a live stream of goals would, and animated obstacles follow the same clock
+    pub fn play_trajectory(&mut self, trajectory: &CartesianTrajectory, rate: f64) -> Result<JointTrajectory, RelaxedIKError> {
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("playback rate should be a positive number of solves per second, got {}", rate)});
+        }
+        if trajectory.num_chains() != self.vars.robot.num_chains {
+            return Err(RelaxedIKError::InvalidGoals{message: format!("the trajectory has poses for {} chains, the robot has {}", trajectory.num_chains(), self.vars.robot.num_chains)});
+        }
+
+        let mut out = JointTrajectory::new(self.vars.robot.joint_ordering.clone());
+        let num_samples = ((trajectory.end_time() - trajectory.start_time()) * rate + 1.0e-9).floor() as usize + 1;
+        for i in 0..num_samples {
+            let t = trajectory.start_time() + i as f64 / rate;
+            self.set_animation_time(t);
+            let res = self.solve(&trajectory.sample(t))?;
+            out.push(t, res.x);
+        }
+        Ok(out)
     }
 
-    pub fn solve_with_user_provided_goals(&mut self, pos_goals: Vec<Vec<f64>>, quat_goals: Vec<Vec<f64>>) -> Vec<f64> {
+    pub fn solve_with_user_provided_goals(&mut self, pos_goals: Vec<Vec<f64>>, quat_goals: Vec<Vec<f64>>) -> Result<SolveResult, RelaxedIKError> {
         let mut ee_sub = EEPoseGoalsSubscriber::new();
+        if pos_goals.len() != quat_goals.len() {
+            return Err(RelaxedIKError::InvalidGoals{message: format!("got {} position goals but {} rotation goals", pos_goals.len(), quat_goals.len())});
+        }
         for i in 0..pos_goals.len() {
+            if pos_goals[i].len() != 3 || quat_goals[i].len() != 4 {
+                return Err(RelaxedIKError::InvalidGoals{message: format!("goal {} should have 3 position and 4 quaternion values, got {} and {}",
+                    i, pos_goals[i].len(), quat_goals[i].len())});
+            }
             ee_sub.pos_goals.push( Vector3::new( pos_goals[i][0], pos_goals[i][1], pos_goals[i][2] ) );
             let tmp_quat = Quaternion::new(quat_goals[i][0], quat_goals[i][1], quat_goals[i][2], quat_goals[i][3]);
             ee_sub.quat_goals.push( UnitQuaternion::from_quaternion(tmp_quat) );
@@ -86,84 +654,79 @@ impl RelaxedIK {
         self.solve(&ee_sub)
     }
 
-    pub fn solve_precise(&mut self, ee_sub: &EEPoseGoalsSubscriber) -> (Vec<f64>) {
-        let mut out_x = self.vars.xopt.clone();
+    // with the default PreciseSolveOptions: 5mm / 0.005rad, at most 100 random restarts
+    pub fn solve_precise(&mut self, ee_sub: &EEPoseGoalsSubscriber) -> Result<PreciseSolveResult, RelaxedIKError> {
+        self.solve_precise_with_options(ee_sub, &PreciseSolveOptions::new())
+    }
 
-        if self.vars.rotation_mode_relative {
-            for i in 0..self.vars.robot.num_chains {
-                self.vars.goal_positions[i] = self.vars.init_ee_positions[i] + ee_sub.pos_goals[i];
-                self.vars.goal_quats[i] = ee_sub.quat_goals[i] * self.vars.init_ee_quats[i];
-            }
-        } else {
-            for i in 0..self.vars.robot.num_chains  {
-                self.vars.goal_positions[i] = ee_sub.pos_goals[i].clone();
-                self.vars.goal_quats[i] = ee_sub.quat_goals[i].clone();
-            }
+    // solves from the current configuration, then from random ones until every goal is within the tolerances or the
+    // restarts / time run out. the solver only moves to the solution when one was found
+    pub fn solve_precise_with_options(&mut self, ee_sub: &EEPoseGoalsSubscriber, options: &PreciseSolveOptions) -> Result<PreciseSolveResult, RelaxedIKError> {
+        if !(options.pos_tolerance >= 0.0 && options.rot_tolerance >= 0.0) {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("precise solve tolerances should be non-negative, got {} and {}", options.pos_tolerance, options.rot_tolerance)});
         }
+        if options.time_limit == Some(Duration::from_secs(0)) {
+            return Err(RelaxedIKError::InvalidArgument{message: "precise solve time limit should be positive".to_string()});
+        }
+        let start = Instant::now();
+        self.set_goals(ee_sub)?;
 
-        self.groove_nlopt.optimize(&mut out_x, &self.vars, &self.om, 200);
+        let mut best: Option<PreciseSolveResult> = None;
+        for attempt in 0..options.max_restarts + 1 {
+            let mut time_left = None;
+            if let Some(limit) = options.time_limit {
+                let elapsed = start.elapsed();
+                // the first attempt always runs, so there is something to return
+                if attempt > 0 && elapsed >= limit {
+                    break;
+                }
+                time_left = Some(limit.checked_sub(elapsed).unwrap_or(Duration::from_secs(0)));
+            }
 
-        let mut max_pos_error = 0.0;
-        let mut max_rot_error = 0.0;
-        let ee_poses = self.vars.robot.get_ee_pos_and_quat_immutable(&out_x);
-        for i in 0..self.vars.robot.num_chains {
-            let pos_error = (self.vars.goal_positions[i] - ee_poses[i].0).norm();
-            let rot_error = angle_between(self.vars.goal_quats[i].clone(), ee_poses[i].1.clone());
-            if pos_error > max_pos_error { max_pos_error = pos_error; }
-            if rot_error > max_rot_error { max_rot_error = rot_error; }
-        }
+            let mut out_x = if attempt == 0 { self.vars.xopt.clone() } else { self.vars.sampler.sample().data.as_vec().clone() };
+            self.groove_nlopt.optimize(&mut out_x, &self.vars, &self.om, 200, time_left);
 
-        while max_pos_error > 0.005 || max_rot_error > 0.005 {
-            let res = self.solve_randstart(ee_sub);
-            out_x = res.1.clone();
-            max_pos_error = 0.0; max_rot_error = 0.0;
-            let ee_poses = self.vars.robot.get_ee_pos_and_quat_immutable(&out_x);
-            for i in 0..self.vars.robot.num_chains {
-                let pos_error = (self.vars.goal_positions[i] - ee_poses[i].0).norm();
-                let rot_error = angle_between(self.vars.goal_quats[i].clone(), ee_poses[i].1.clone());
-                if pos_error > max_pos_error { max_pos_error = pos_error; }
-                if rot_error > max_rot_error { max_rot_error = rot_error; }
+            let (pos_errors, rot_errors) = self.get_ee_errors(&out_x);
+            let found = within_tolerances(&pos_errors, &rot_errors, options);
+            let better = match &best {
+                Some(b) => max_error(&pos_errors, &rot_errors) < max_error(&b.pos_errors, &b.rot_errors),
+                None => true
+            };
+            if found || better {
+                best = Some(PreciseSolveResult{x: out_x, found, attempt, pos_errors, rot_errors, solve_time: start.elapsed()});
+            }
+            if found {
+                break;
             }
         }
 
-        self.vars.update(out_x.clone());
-        self.vars.update_collision_world();
-
-        out_x
+        let mut result = best.unwrap();
+        result.solve_time = start.elapsed();
+        if result.found {
+            self.vars.update(result.x.clone());
+            self.vars.update_collision_world();
+        }
+        Ok(result)
     }
 
-    pub fn solve_randstart(&mut self, ee_sub: &EEPoseGoalsSubscriber) -> (bool, Vec<f64>) {
+    pub fn solve_randstart(&mut self, ee_sub: &EEPoseGoalsSubscriber) -> Result<(bool, Vec<f64>), RelaxedIKError> {
         let mut out_x = self.vars.sampler.sample().data.as_vec().clone();
 
-        if self.vars.rotation_mode_relative {
-            for i in 0..self.vars.robot.num_chains {
-                self.vars.goal_positions[i] = self.vars.init_ee_positions[i] + ee_sub.pos_goals[i];
-                self.vars.goal_quats[i] = ee_sub.quat_goals[i] * self.vars.init_ee_quats[i];
-            }
-        } else {
-            for i in 0..self.vars.robot.num_chains  {
-                self.vars.goal_positions[i] = ee_sub.pos_goals[i].clone();
-                self.vars.goal_quats[i] = ee_sub.quat_goals[i].clone();
-            }
-        }
-
-        self.groove_nlopt.optimize(&mut out_x, &self.vars, &self.om, 200);
+        self.set_goals(ee_sub)?;
 
-        let mut max_pos_error = 0.0;
-        let mut max_rot_error = 0.0;
-        let ee_poses = self.vars.robot.get_ee_pos_and_quat_immutable(&out_x);
-        for i in 0..self.vars.robot.num_chains {
-            let pos_error = (self.vars.goal_positions[i] - ee_poses[i].0).norm();
-            let rot_error = angle_between(self.vars.goal_quats[i].clone(), ee_poses[i].1.clone());
-            if pos_error > max_pos_error {max_pos_error = pos_error;}
-            if rot_error > max_rot_error {max_rot_error = rot_error;}
-        }
+        self.groove_nlopt.optimize(&mut out_x, &self.vars, &self.om, 200, None);
 
-        if max_pos_error > 0.005 || max_rot_error > 0.005 {
-            return (false, out_x)
-        } else {
-            // self.vars.update(out_x.clone());
-            return (true, out_x)
-        }
+        let (pos_errors, rot_errors) = self.get_ee_errors(&out_x);
+        // self.vars.update(out_x.clone());
+        Ok((within_tolerances(&pos_errors, &rot_errors, &PreciseSolveOptions::new()), out_x))
     }
 }
+
+fn within_tolerances(pos_errors: &Vec<f64>, rot_errors: &Vec<f64>, options: &PreciseSolveOptions) -> bool {
+    pos_errors.iter().all(|e| *e <= options.pos_tolerance) && rot_errors.iter().all(|e| *e <= options.rot_tolerance)
+}
+
+// to rank attempts that missed, position and rotation errors are compared as they are (m against rad)
+fn max_error(pos_errors: &Vec<f64>, rot_errors: &Vec<f64>) -> f64 {
+    pos_errors.iter().chain(rot_errors.iter()).cloned().fold(0.0, f64::max)
+}
@@ -1,67 +1,666 @@
 use crate::relaxed_ik;
-use crate::utils_rust::subscriber_utils::EEPoseGoalsSubscriber;
-use std::sync::{Arc, Mutex};
+use crate::utils_rust::subscriber_utils::{EEPoseGoalsSubscriber, GoalFrame};
+use crate::utils_rust::error_utils::RelaxedIKError;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
 use nalgebra::{Vector3, UnitQuaternion, Quaternion,Translation3, Isometry3};
+use ncollide3d::shape::{Ball, Cuboid, ShapeHandle};
 use std::os::raw::{*};
+use std::time::{Duration, Instant};
 
+// status codes returned by the c api, anything but RELAXED_IK_OK leaves a message for last_error_message
+pub const RELAXED_IK_OK: c_int = 0;
+pub const RELAXED_IK_ERR_NULL_POINTER: c_int = -1;
+pub const RELAXED_IK_ERR_INVALID_ARGUMENT: c_int = -2;
+pub const RELAXED_IK_ERR_CONFIG: c_int = -3;
+pub const RELAXED_IK_ERR_INVALID_GOALS: c_int = -4;
+pub const RELAXED_IK_ERR_SOLVER: c_int = -5;
+pub const RELAXED_IK_ERR_PANIC: c_int = -6;
+
+// opaque to C, every handle owns its own solver so several robots / configurations can live in one process,
+// and the mutex lets a handle be shared between threads
+pub struct RelaxedIKHandle {
+    r: Mutex<relaxed_ik::RelaxedIK>,
+    // handed out by relaxed_ik_joint_name and relaxed_ik_link_name, so they have to live as long as the handle
+    joint_names: Vec<CString>,
+    // or why the robot has no link names, see Robot::link_names
+    link_names: Result<Vec<CString>, String>
+}
+
+impl RelaxedIKHandle {
+    fn new(r: relaxed_ik::RelaxedIK) -> Self {
+        let mut joint_names: Vec<CString> = Vec::new();
+        for i in 0..r.vars.robot.joint_ordering.len() {
+            joint_names.push(CString::new(r.vars.robot.joint_ordering[i].replace('\0', " ")).unwrap());
+        }
+        // in the order relaxed_ik_link_poses writes them: chain by chain, the joints' links, then ee_link_<chain> and tcp_<chain>
+        let link_names = r.vars.robot.link_names().map(|chain_names| {
+            let mut link_names: Vec<CString> = Vec::new();
+            for i in 0..chain_names.len() {
+                for j in 0..chain_names[i].len() {
+                    link_names.push(CString::new(chain_names[i][j].replace('\0', " ")).unwrap());
+                }
+                link_names.push(CString::new(format!("ee_link_{}", i)).unwrap());
+                link_names.push(CString::new(format!("tcp_{}", i)).unwrap());
+            }
+            link_names
+        }).map_err(|e| e.to_string());
+        RelaxedIKHandle{r: Mutex::new(r), joint_names, link_names}
+    }
+
+    fn link_names(&self) -> Result<&Vec<CString>, FfiError> {
+        self.link_names.as_ref().map_err(|message| FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT, message: message.clone()})
+    }
+}
+
+// backs the handle-free dynamic_obstacle_cb and solve kept for existing wrappers. a load error is kept rather than
+// panicking, a panic here would poison the lazy static and take every later call down with it
 lazy_static! {
-    static ref R: Mutex<relaxed_ik::RelaxedIK> = Mutex::new(relaxed_ik::RelaxedIK::from_loaded(1));
+    static ref R: Result<RelaxedIKHandle, String> = relaxed_ik::RelaxedIK::from_loaded(1).map(RelaxedIKHandle::new).map_err(|e| e.to_string());
+}
+
+fn loaded_handle() -> Result<&'static RelaxedIKHandle, FfiError> {
+    match &*R {
+        Ok(h) => Ok(h),
+        Err(message) => Err(FfiError{status: RELAXED_IK_ERR_CONFIG, message: format!("could not load relaxed ik: {}", message)})
+    }
+}
+
+// per thread, like errno, so a failing call on one thread can't clobber the message another thread is reading
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+struct FfiError {
+    status: c_int,
+    message: String
+}
+
+impl FfiError {
+    fn new(status: c_int, message: &str) -> Self {
+        FfiError{status, message: message.to_string()}
+    }
 }
 
+impl From<RelaxedIKError> for FfiError {
+    fn from(e: RelaxedIKError) -> Self {
+        let status = match e {
+            RelaxedIKError::InvalidGoals{..} => RELAXED_IK_ERR_INVALID_GOALS,
+            RelaxedIKError::Solver{..} => RELAXED_IK_ERR_SOLVER,
+            RelaxedIKError::InvalidArgument{..} | RelaxedIKError::InvalidObstacle{..} => RELAXED_IK_ERR_INVALID_ARGUMENT,
+            _ => RELAXED_IK_ERR_CONFIG
+        };
+        FfiError{status, message: e.to_string()}
+    }
+}
+
+fn set_last_error(message: String) {
+    // interior nul bytes would truncate the message on the C side anyway
+    let message = CString::new(message.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+// runs f, turning errors and panics into a status code so nothing unwinds into the caller
+fn ffi_call<F: FnOnce() -> Result<(), FfiError>>(f: F) -> c_int {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => RELAXED_IK_OK,
+        Ok(Err(e)) => {
+            set_last_error(e.message);
+            e.status
+        }
+        Err(p) => {
+            let message = if let Some(s) = p.downcast_ref::<&str>() {
+                s.to_string()
+            } else if let Some(s) = p.downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "unknown panic".to_string()
+            };
+            set_last_error(format!("relaxed ik panicked: {}", message));
+            RELAXED_IK_ERR_PANIC
+        }
+    }
+}
+
+// message for the last failed call on this thread, or null. owned by the library and valid until the next failing call on the same thread
 #[no_mangle]
-pub unsafe extern "C" fn dynamic_obstacle_cb(name: *const c_char, pos_arr: *const c_double, quat_arr: *const c_double) {
-    assert!(!name.is_null(), "Empty name!");
-    assert!(!pos_arr.is_null(), "Null pointer for pos!");
-    assert!(!quat_arr.is_null(), "Null pointer for quat!");
+pub extern "C" fn last_error_message() -> *const c_char {
+    LAST_ERROR.with(|e| match &*e.borrow() {
+        Some(s) => s.as_ptr(),
+        None => std::ptr::null()
+    })
+}
+
+// path is an info file; a null path loads the robot named in settings.yaml, like the handle-free functions do.
+// returns null if the solver could not be built
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_new(path: *const c_char) -> *mut RelaxedIKHandle {
+    let mut handle: *mut RelaxedIKHandle = std::ptr::null_mut();
+    ffi_call(|| {
+        let r = if path.is_null() {
+            relaxed_ik::RelaxedIK::from_loaded(1)?
+        } else {
+            relaxed_ik::RelaxedIK::from_yaml_path(c_str_arg(path, "path")?, 1)?
+        };
+        handle = Box::into_raw(Box::new(RelaxedIKHandle::new(r)));
+        Ok(())
+    });
+    handle
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_free(handle: *mut RelaxedIKHandle) {
+    if handle.is_null() {
+        return;
+    }
+    drop(Box::from_raw(handle));
+}
 
-    let c_str = std::ffi::CStr::from_ptr(name);
-    let name_str = c_str.to_str().expect("Not a valid UTF-8 string");
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_dynamic_obstacle_cb(handle: *mut RelaxedIKHandle, name: *const c_char, pos_arr: *const c_double, quat_arr: *const c_double) -> c_int {
+    ffi_call(|| dynamic_obstacle_helper(handle_arg(handle)?, name, pos_arr, quat_arr))
+}
 
-    let pos_slice: &[c_double] = std::slice::from_raw_parts(pos_arr, 3);
-    let quat_slice: &[c_double] = std::slice::from_raw_parts(quat_arr, 4);
+// on success out holds a buffer of num_dof joint values that must be released with free_opt
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_solve(handle: *mut RelaxedIKHandle, pos_arr: *const c_double, pos_length: c_int,
+    quat_arr: *const c_double, quat_length: c_int, out: *mut relaxed_ik::Opt) -> c_int {
+    ffi_call(|| {
+        if out.is_null() {
+            return Err(FfiError::new(RELAXED_IK_ERR_NULL_POINTER, "null pointer for out"));
+        }
+        let x = solve_helper(handle_arg(handle)?, pos_arr, pos_length, quat_arr, quat_length)?;
+        *out = opt_from_vec(x);
+        Ok(())
+    })
+}
 
-    let pos_vec = pos_slice.to_vec();
-    let quat_vec = quat_slice.to_vec();
+// same as relaxed_ik_solve, but writes into a caller owned buffer of at least num_dof values
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_solve_into(handle: *mut RelaxedIKHandle, pos_arr: *const c_double, pos_length: c_int,
+    quat_arr: *const c_double, quat_length: c_int, out_arr: *mut c_double, out_length: c_int) -> c_int {
+    ffi_call(|| {
+        if out_arr.is_null() {
+            return Err(FfiError::new(RELAXED_IK_ERR_NULL_POINTER, "null pointer for out_arr"));
+        }
+        let x = solve_helper(handle_arg(handle)?, pos_arr, pos_length, quat_arr, quat_length)?;
+        if (out_length as i64) < x.len() as i64 {
+            return Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT,
+                message: format!("out_arr holds {} values, the robot has {} dof", out_length, x.len())});
+        }
+        std::ptr::copy_nonoverlapping(x.as_ptr(), out_arr, x.len());
+        Ok(())
+    })
+}
 
-    let ts = Translation3::new(pos_vec[0], pos_vec[1], pos_vec[2]);
-    let tmp_q = Quaternion::new(quat_vec[3], quat_vec[0], quat_vec[1], quat_vec[2]);
-    let rot = UnitQuaternion::from_quaternion(tmp_q);
-    let pos = Isometry3::from_parts(ts, rot);
+// relaxed_ik_solve_into with a time budget in seconds, counted from the call. deadline_hit is set to 1 if the solver ran out
+// of time, out_arr then holds the best configuration found so far
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_solve_within(handle: *mut RelaxedIKHandle, pos_arr: *const c_double, pos_length: c_int,
+    quat_arr: *const c_double, quat_length: c_int, time_budget: c_double, out_arr: *mut c_double, out_length: c_int, deadline_hit: *mut c_int) -> c_int {
+    let deadline = Instant::now();
+    ffi_call(|| {
+        if out_arr.is_null() || deadline_hit.is_null() {
+            return Err(FfiError::new(RELAXED_IK_ERR_NULL_POINTER, "null pointer for out_arr or deadline_hit"));
+        }
+        if !(time_budget.is_finite() && time_budget >= 0.0) {
+            return Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT, message: format!("time_budget should be a non-negative number of seconds, got {}", time_budget)});
+        }
+        // a deadline past what Instant can hold is no deadline at all
+        let deadline = deadline.checked_add(Duration::from_secs_f64(time_budget.min(1.0e9)));
+        let mut r = lock(handle_arg(handle)?)?;
+        let g = goals_helper(r.vars.robot.num_chains, pos_arr, pos_length, quat_arr, quat_length)?;
+        let res = match deadline {
+            Some(d) => r.solve_with_deadline(&g, d)?,
+            None => r.solve(&g)?
+        };
+        if (out_length as i64) < res.x.len() as i64 {
+            return Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT,
+                message: format!("out_arr holds {} values, the robot has {} dof", out_length, res.x.len())});
+        }
+        std::ptr::copy_nonoverlapping(res.x.as_ptr(), out_arr, res.x.len());
+        *deadline_hit = if res.deadline_hit { 1 } else { 0 };
+        Ok(())
+    })
+}
 
-    R.lock().unwrap().vars.env_collision.update_dynamic_obstacle(name_str, pos);
+// restarts from random configurations until every goal is reached within 5mm / 0.005rad, giving up with RELAXED_IK_ERR_SOLVER
+// after 100 restarts. release out with free_opt
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_solve_precise(handle: *mut RelaxedIKHandle, pos_arr: *const c_double, pos_length: c_int,
+    quat_arr: *const c_double, quat_length: c_int, out: *mut relaxed_ik::Opt) -> c_int {
+    ffi_call(|| {
+        if out.is_null() {
+            return Err(FfiError::new(RELAXED_IK_ERR_NULL_POINTER, "null pointer for out"));
+        }
+        let mut r = lock(handle_arg(handle)?)?;
+        let g = goals_helper(r.vars.robot.num_chains, pos_arr, pos_length, quat_arr, quat_length)?;
+        let options = relaxed_ik::PreciseSolveOptions::new();
+        let res = r.solve_precise_with_options(&g, &options)?;
+        if !res.found {
+            return Err(RelaxedIKError::Solver{message: format!("no configuration within {}m / {}rad of the goals after {} restarts",
+                options.pos_tolerance, options.rot_tolerance, options.max_restarts)}.into());
+        }
+        *out = opt_from_vec(res.x);
+        Ok(())
+    })
 }
 
+// relaxed_ik_solve_precise with its limits given: at most max_restarts random restarts, and time_limit seconds in total if it is
+// positive. out_arr gets the first configuration within the tolerances, found is set to 1, and attempt to the restart that
+// produced it (0 for the solve from the current configuration). if none is, found is set to 0 and out_arr gets the closest one
 #[no_mangle]
-pub unsafe extern "C" fn solve(pos_arr: *const c_double, pos_length: c_int, 
-    quat_arr: *const c_double, quat_length: c_int) -> relaxed_ik::Opt {
-    assert!(!pos_arr.is_null(), "Null pointer for pos goals!");
-    assert!(!quat_arr.is_null(), "Null pointer for quat goals!");
+pub unsafe extern "C" fn relaxed_ik_solve_precise_with_options(handle: *mut RelaxedIKHandle, pos_arr: *const c_double, pos_length: c_int,
+    quat_arr: *const c_double, quat_length: c_int, pos_tolerance: c_double, rot_tolerance: c_double, max_restarts: c_int, time_limit: c_double,
+    out_arr: *mut c_double, out_length: c_int, found: *mut c_int, attempt: *mut c_int) -> c_int {
+    ffi_call(|| {
+        if out_arr.is_null() || found.is_null() || attempt.is_null() {
+            return Err(FfiError::new(RELAXED_IK_ERR_NULL_POINTER, "null pointer for out_arr, found or attempt"));
+        }
+        if max_restarts < 0 || time_limit.is_nan() {
+            return Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT, message: format!("got {} max restarts and a time limit of {}", max_restarts, time_limit)});
+        }
+        let mut options = relaxed_ik::PreciseSolveOptions::new();
+        options.pos_tolerance = pos_tolerance;
+        options.rot_tolerance = rot_tolerance;
+        options.max_restarts = max_restarts as usize;
+        if time_limit > 0.0 {
+            options.time_limit = Some(Duration::from_secs_f64(time_limit.min(1.0e9)));
+        }
 
-    let pos_slice: &[c_double] = std::slice::from_raw_parts(pos_arr, pos_length as usize);
-    let quat_slice: &[c_double] = std::slice::from_raw_parts(quat_arr, quat_length as usize);
+        let mut r = lock(handle_arg(handle)?)?;
+        let g = goals_helper(r.vars.robot.num_chains, pos_arr, pos_length, quat_arr, quat_length)?;
+        let res = r.solve_precise_with_options(&g, &options)?;
+        if (out_length as i64) < res.x.len() as i64 {
+            return Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT,
+                message: format!("out_arr holds {} values, the robot has {} dof", out_length, res.x.len())});
+        }
+        std::ptr::copy_nonoverlapping(res.x.as_ptr(), out_arr, res.x.len());
+        *found = if res.found { 1 } else { 0 };
+        *attempt = res.attempt as c_int;
+        Ok(())
+    })
+}
 
-    let pos_vec = pos_slice.to_vec();
-    let quat_vec = quat_slice.to_vec();
+// a single solve from a random configuration, success is set to 1 if it reached every goal and 0 otherwise.
+// the solver keeps its current configuration, release out with free_opt
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_solve_randstart(handle: *mut RelaxedIKHandle, pos_arr: *const c_double, pos_length: c_int,
+    quat_arr: *const c_double, quat_length: c_int, out: *mut relaxed_ik::Opt, success: *mut c_int) -> c_int {
+    ffi_call(|| {
+        if out.is_null() || success.is_null() {
+            return Err(FfiError::new(RELAXED_IK_ERR_NULL_POINTER, "null pointer for out or success"));
+        }
+        let mut r = lock(handle_arg(handle)?)?;
+        let g = goals_helper(r.vars.robot.num_chains, pos_arr, pos_length, quat_arr, quat_length)?;
+        let (reached, x) = r.solve_randstart(&g)?;
+        *out = opt_from_vec(x);
+        *success = if reached { 1 } else { 0 };
+        Ok(())
+    })
+}
+
+// link poses in the robot's base frame for one or more joint configs. x_arr holds the configs back to back, so x_length is
+// a multiple of the dof. for each config, relaxed_ik_num_link_poses poses in relaxed_ik_link_name order go into out_arr,
+// 7 values each (x, y, z, qx, qy, qz, qw)
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_link_poses(handle: *mut RelaxedIKHandle, x_arr: *const c_double, x_length: c_int,
+    out_arr: *mut c_double, out_length: c_int) -> c_int {
+    ffi_call(|| {
+        let x = f64_slice_arg(x_arr, x_length, "x")?;
+        let h = handle_arg(handle)?;
+        let r = lock(h)?;
+        let num_dof = r.vars.robot.num_dof;
+        if num_dof == 0 || x.is_empty() || x.len() % num_dof != 0 {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("got {} joint values, should be a multiple of the robot's {} dof", x.len(), num_dof)}.into());
+        }
+        let xs: Vec<Vec<f64>> = x.chunks(num_dof).map(|c| c.to_vec()).collect();
+        let out = f64_slice_out_arg(out_arr, out_length, 7 * h.link_names()?.len() * xs.len(), "out_arr")?;
+
+        let all_poses = r.link_poses_batch(&xs)?;
+        let mut k = 0;
+        for i in 0..all_poses.len() {
+            for c in 0..all_poses[i].len() {
+                let chain_poses = &all_poses[i][c];
+                let mut poses: Vec<Isometry3<f64>> = chain_poses.joints.iter().map(|(_, pose)| *pose).collect();
+                poses.push(chain_poses.ee_link);
+                poses.push(chain_poses.tcp);
+                for j in 0..poses.len() {
+                    let q = poses[j].rotation.quaternion();
+                    out[k..k + 7].copy_from_slice(&[poses[j].translation.vector[0], poses[j].translation.vector[1], poses[j].translation.vector[2],
+                        q.i, q.j, q.k, q.w]);
+                    k += 7;
+                }
+            }
+        }
+        Ok(())
+    })
+}
 
-    let ja = solve_helper(pos_vec, quat_vec);
-    let ptr = ja.as_ptr();
-    let len = ja.len();
-    std::mem::forget(ja);
+// end effector poses at x, 3 values per chain into pos_out and 4 (x, y, z, w) per chain into quat_out
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_forward_kinematics(handle: *mut RelaxedIKHandle, x_arr: *const c_double, x_length: c_int,
+    pos_out: *mut c_double, pos_out_length: c_int, quat_out: *mut c_double, quat_out_length: c_int) -> c_int {
+    ffi_call(|| {
+        let x = f64_slice_arg(x_arr, x_length, "x")?;
+        let r = lock(handle_arg(handle)?)?;
+        let num_chains = r.vars.robot.num_chains;
+        if x.len() != r.vars.robot.num_dof {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("got {} joint values for a robot with {} dof", x.len(), r.vars.robot.num_dof)}.into());
+        }
+        let pos_out = f64_slice_out_arg(pos_out, pos_out_length, 3 * num_chains, "pos_out")?;
+        let quat_out = f64_slice_out_arg(quat_out, quat_out_length, 4 * num_chains, "quat_out")?;
+
+        let ee_poses = r.vars.robot.get_ee_pos_and_quat_immutable(x);
+        for i in 0..num_chains {
+            for j in 0..3 {
+                pos_out[3*i + j] = ee_poses[i].0[j];
+            }
+            let q = ee_poses[i].1.quaternion();
+            quat_out[4*i] = q.i;
+            quat_out[4*i + 1] = q.j;
+            quat_out[4*i + 2] = q.k;
+            quat_out[4*i + 3] = q.w;
+        }
+        Ok(())
+    })
+}
+
+// moves the solver to x (num_dof values) and forgets the motion history, so the next solve starts there
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_reset(handle: *mut RelaxedIKHandle, x_arr: *const c_double, x_length: c_int) -> c_int {
+    ffi_call(|| {
+        let x = f64_slice_arg(x_arr, x_length, "x")?.to_vec();
+        lock(handle_arg(handle)?)?.reset(x)?;
+        Ok(())
+    })
+}
+
+// one of noECA, ECA, ECA3 or ECAA, see settings.yaml
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_set_objective_mode(handle: *mut RelaxedIKHandle, objective_mode: *const c_char) -> c_int {
+    ffi_call(|| {
+        let mode = c_str_arg(objective_mode, "objective_mode")?;
+        lock(handle_arg(handle)?)?.set_objective_mode(mode.as_str())?;
+        Ok(())
+    })
+}
+
+// absolute, relative, relative_to_current or relative_to_tool for each, see settings.yaml
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_set_goal_frames(handle: *mut RelaxedIKHandle, position_mode: *const c_char, rotation_mode: *const c_char) -> c_int {
+    ffi_call(|| {
+        let position_mode = goal_frame_arg(position_mode, "position_mode")?;
+        let rotation_mode = goal_frame_arg(rotation_mode, "rotation_mode")?;
+        lock(handle_arg(handle)?)?.set_goal_frames(position_mode, rotation_mode);
+        Ok(())
+    })
+}
+
+// tool center point of a chain in the frame of its last joint: position (3 values) and an optional (x, y, z, w)
+// quaternion, identity rotation if quat_arr is null
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_set_tcp(handle: *mut RelaxedIKHandle, chain: c_int, pos_arr: *const c_double, quat_arr: *const c_double) -> c_int {
+    ffi_call(|| {
+        if chain < 0 {
+            return Err(FfiError::new(RELAXED_IK_ERR_INVALID_ARGUMENT, "negative chain index"));
+        }
+        let tcp = pose_arg(pos_arr, quat_arr)?;
+        lock(handle_arg(handle)?)?.set_tcp(chain as usize, tcp)?;
+        Ok(())
+    })
+}
+
+// number of joints in a solution, or a negative status code
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_num_dof(handle: *mut RelaxedIKHandle) -> c_int {
+    let mut num_dof = 0;
+    let status = ffi_call(|| {
+        num_dof = lock(handle_arg(handle)?)?.vars.robot.num_dof as c_int;
+        Ok(())
+    });
+    if status == RELAXED_IK_OK { num_dof } else { status }
+}
+
+// number of end effectors (goals per solve), or a negative status code
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_num_chains(handle: *mut RelaxedIKHandle) -> c_int {
+    let mut num_chains = 0;
+    let status = ffi_call(|| {
+        num_chains = lock(handle_arg(handle)?)?.vars.robot.num_chains as c_int;
+        Ok(())
+    });
+    if status == RELAXED_IK_OK { num_chains } else { status }
+}
+
+// number of poses relaxed_ik_link_poses writes per joint config, or a negative status code
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_num_link_poses(handle: *mut RelaxedIKHandle) -> c_int {
+    let mut num_link_poses = 0;
+    let status = ffi_call(|| {
+        num_link_poses = handle_arg(handle)?.link_names()?.len() as c_int;
+        Ok(())
+    });
+    if status == RELAXED_IK_OK { num_link_poses } else { status }
+}
+
+// name of pose link_idx of relaxed_ik_link_poses, or null. a joint name stands for the link that joint moves, a joint
+// shared by several chains shows up once per chain. owned by the handle and valid until relaxed_ik_free
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_link_name(handle: *mut RelaxedIKHandle, link_idx: c_int) -> *const c_char {
+    let mut name: *const c_char = std::ptr::null();
+    ffi_call(|| {
+        let link_names = handle_arg(handle)?.link_names()?;
+        if link_idx < 0 || link_idx as usize >= link_names.len() {
+            return Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT,
+                message: format!("link index {} out of range, the robot has {} link poses", link_idx, link_names.len())});
+        }
+        name = link_names[link_idx as usize].as_ptr();
+        Ok(())
+    });
+    name
+}
+
+// name of joint joint_idx in solution order, or null. owned by the handle and valid until relaxed_ik_free
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_joint_name(handle: *mut RelaxedIKHandle, joint_idx: c_int) -> *const c_char {
+    let mut name: *const c_char = std::ptr::null();
+    ffi_call(|| {
+        let h = handle_arg(handle)?;
+        if joint_idx < 0 || joint_idx as usize >= h.joint_names.len() {
+            return Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT,
+                message: format!("joint index {} out of range for a robot with {} dof", joint_idx, h.joint_names.len())});
+        }
+        name = h.joint_names[joint_idx as usize].as_ptr();
+        Ok(())
+    });
+    name
+}
+
+// is_dynamic != 0 lets relaxed_ik_dynamic_obstacle_cb move the obstacle afterwards
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_add_sphere_obstacle(handle: *mut RelaxedIKHandle, name: *const c_char, radius: c_double,
+    pos_arr: *const c_double, is_dynamic: c_int) -> c_int {
+    ffi_call(|| {
+        let name_str = c_str_arg(name, "name")?;
+        if !(radius > 0.0) {
+            return Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT, message: format!("sphere radius should be positive, got {}", radius)});
+        }
+        let pos = pose_arg(pos_arr, std::ptr::null())?;
+        lock(handle_arg(handle)?)?.vars.env_collision.add_obstacle(name_str.as_str(), ShapeHandle::new(Ball::new(radius)), pos, is_dynamic != 0)?;
+        Ok(())
+    })
+}
+
+// half_extents_arr holds the 3 half lengths of the box, like the scale of cuboids in settings.yaml
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_add_cuboid_obstacle(handle: *mut RelaxedIKHandle, name: *const c_char, half_extents_arr: *const c_double,
+    pos_arr: *const c_double, quat_arr: *const c_double, is_dynamic: c_int) -> c_int {
+    ffi_call(|| {
+        let name_str = c_str_arg(name, "name")?;
+        let half_extents = f64_slice_arg(half_extents_arr, 3, "half_extents")?;
+        if !half_extents.iter().all(|h| *h > 0.0) {
+            return Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT, message: format!("cuboid half extents should be positive, got {:?}", half_extents)});
+        }
+        if quat_arr.is_null() {
+            return Err(FfiError::new(RELAXED_IK_ERR_NULL_POINTER, "null pointer for quat"));
+        }
+        let pos = pose_arg(pos_arr, quat_arr)?;
+        let cuboid = Cuboid::new(Vector3::new(half_extents[0], half_extents[1], half_extents[2]));
+        lock(handle_arg(handle)?)?.vars.env_collision.add_obstacle(name_str.as_str(), ShapeHandle::new(cuboid), pos, is_dynamic != 0)?;
+        Ok(())
+    })
+}
+
+// removes an obstacle added here or loaded from settings.yaml
+#[no_mangle]
+pub unsafe extern "C" fn relaxed_ik_remove_obstacle(handle: *mut RelaxedIKHandle, name: *const c_char) -> c_int {
+    ffi_call(|| {
+        let name_str = c_str_arg(name, "name")?;
+        lock(handle_arg(handle)?)?.vars.env_collision.remove_obstacle(name_str.as_str())?;
+        Ok(())
+    })
+}
+
+// releases a buffer returned by solve or relaxed_ik_solve, empty buffers are ignored
+#[no_mangle]
+pub unsafe extern "C" fn free_opt(opt: relaxed_ik::Opt) {
+    if opt.data.is_null() || opt.length <= 0 {
+        return;
+    }
+    drop(Box::from_raw(std::slice::from_raw_parts_mut(opt.data as *mut c_double, opt.length as usize)));
+}
+
+// errors are reported through last_error_message
+#[no_mangle]
+pub unsafe extern "C" fn dynamic_obstacle_cb(name: *const c_char, pos_arr: *const c_double, quat_arr: *const c_double) {
+    ffi_call(|| dynamic_obstacle_helper(loaded_handle()?, name, pos_arr, quat_arr));
+}
+
+// returns an empty Opt on failure, see last_error_message. release the result with free_opt
+#[no_mangle]
+pub unsafe extern "C" fn solve(pos_arr: *const c_double, pos_length: c_int,
+    quat_arr: *const c_double, quat_length: c_int) -> relaxed_ik::Opt {
+    let mut opt = relaxed_ik::Opt {data: std::ptr::null(), length: 0};
+    ffi_call(|| {
+        let x = solve_helper(loaded_handle()?, pos_arr, pos_length, quat_arr, quat_length)?;
+        opt = opt_from_vec(x);
+        Ok(())
+    });
+    opt
+}
+
+fn opt_from_vec(x: Vec<f64>) -> relaxed_ik::Opt {
+    // boxed so capacity == length, which free_opt relies on
+    let b = x.into_boxed_slice();
+    let len = b.len();
+    let ptr = Box::into_raw(b) as *const c_double;
     relaxed_ik::Opt {data: ptr, length: len as c_int}
 }
 
-fn solve_helper(pos_goals: Vec<f64>, quat_goals: Vec<f64>) -> Vec<f64> {
-    let arc = Arc::new(Mutex::new(EEPoseGoalsSubscriber::new()));
-    let mut g = arc.lock().unwrap();
-    
-    for i in 0..R.lock().unwrap().vars.robot.num_chains {
+unsafe fn handle_arg<'a>(handle: *mut RelaxedIKHandle) -> Result<&'a RelaxedIKHandle, FfiError> {
+    if handle.is_null() {
+        return Err(FfiError::new(RELAXED_IK_ERR_NULL_POINTER, "null relaxed ik handle"));
+    }
+    Ok(&*handle)
+}
+
+unsafe fn c_str_arg(s: *const c_char, what: &str) -> Result<String, FfiError> {
+    if s.is_null() {
+        return Err(FfiError{status: RELAXED_IK_ERR_NULL_POINTER, message: format!("null pointer for {}", what)});
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Ok(s.to_string()),
+        Err(_) => Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT, message: format!("{} is not valid utf-8", what)})
+    }
+}
+
+unsafe fn goal_frame_arg(s: *const c_char, what: &str) -> Result<GoalFrame, FfiError> {
+    let name = c_str_arg(s, what)?;
+    match GoalFrame::from_name(name.as_str()) {
+        Some(frame) => Ok(frame),
+        None => Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT, message: format!("unknown goal frame {} for {}", name, what)})
+    }
+}
+
+unsafe fn f64_slice_arg<'a>(arr: *const c_double, length: c_int, what: &str) -> Result<&'a [c_double], FfiError> {
+    if arr.is_null() {
+        return Err(FfiError{status: RELAXED_IK_ERR_NULL_POINTER, message: format!("null pointer for {}", what)});
+    }
+    if length < 0 {
+        return Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT, message: format!("negative length {} for {}", length, what)});
+    }
+    Ok(std::slice::from_raw_parts(arr, length as usize))
+}
+
+unsafe fn f64_slice_out_arg<'a>(arr: *mut c_double, length: c_int, needed: usize, what: &str) -> Result<&'a mut [c_double], FfiError> {
+    if arr.is_null() {
+        return Err(FfiError{status: RELAXED_IK_ERR_NULL_POINTER, message: format!("null pointer for {}", what)});
+    }
+    if length < 0 || (length as usize) < needed {
+        return Err(FfiError{status: RELAXED_IK_ERR_INVALID_ARGUMENT, message: format!("{} holds {} values, {} are needed", what, length, needed)});
+    }
+    Ok(std::slice::from_raw_parts_mut(arr, needed))
+}
+
+// position (3 values) and an optional (x, y, z, w) quaternion, identity rotation if quat_arr is null
+unsafe fn pose_arg(pos_arr: *const c_double, quat_arr: *const c_double) -> Result<Isometry3<f64>, FfiError> {
+    let pos_vec = f64_slice_arg(pos_arr, 3, "pos")?;
+    let ts = Translation3::new(pos_vec[0], pos_vec[1], pos_vec[2]);
+    let rot = if quat_arr.is_null() {
+        UnitQuaternion::identity()
+    } else {
+        let quat_vec = f64_slice_arg(quat_arr, 4, "quat")?;
+        UnitQuaternion::from_quaternion(Quaternion::new(quat_vec[3], quat_vec[0], quat_vec[1], quat_vec[2]))
+    };
+    Ok(Isometry3::from_parts(ts, rot))
+}
+
+fn lock(handle: &RelaxedIKHandle) -> Result<std::sync::MutexGuard<'_, relaxed_ik::RelaxedIK>, FfiError> {
+    // poisoned by a panic in an earlier call, the solver state can't be trusted anymore
+    handle.r.lock().map_err(|_| FfiError::new(RELAXED_IK_ERR_PANIC, "relaxed ik handle is unusable after an earlier panic"))
+}
+
+unsafe fn dynamic_obstacle_helper(handle: &RelaxedIKHandle, name: *const c_char, pos_arr: *const c_double, quat_arr: *const c_double) -> Result<(), FfiError> {
+    let name_str = c_str_arg(name, "name")?;
+    if quat_arr.is_null() {
+        return Err(FfiError::new(RELAXED_IK_ERR_NULL_POINTER, "null pointer for quat"));
+    }
+    let pos = pose_arg(pos_arr, quat_arr)?;
+
+    lock(handle)?.vars.env_collision.update_dynamic_obstacle(name_str.as_str(), pos);
+    Ok(())
+}
+
+unsafe fn goals_helper(num_chains: usize, pos_arr: *const c_double, pos_length: c_int,
+    quat_arr: *const c_double, quat_length: c_int) -> Result<EEPoseGoalsSubscriber, FfiError> {
+    let pos_goals = f64_slice_arg(pos_arr, pos_length, "pos goals")?;
+    let quat_goals = f64_slice_arg(quat_arr, quat_length, "quat goals")?;
+
+    if pos_goals.len() < 3 * num_chains || quat_goals.len() < 4 * num_chains {
+        return Err(RelaxedIKError::InvalidGoals{message: format!("got {} position and {} quaternion values for {} chains",
+            pos_goals.len(), quat_goals.len(), num_chains)}.into());
+    }
+
+    let mut g = EEPoseGoalsSubscriber::new();
+    for i in 0..num_chains {
         g.pos_goals.push( Vector3::new(pos_goals[3*i], pos_goals[3*i+1], pos_goals[3*i+2]) );
         let tmp_q = Quaternion::new(quat_goals[4*i+3], quat_goals[4*i], quat_goals[4*i+1], quat_goals[4*i+2]);
         g.quat_goals.push( UnitQuaternion::from_quaternion(tmp_q) );
     }
-    
-    let x = R.lock().unwrap().solve(&g);
+    Ok(g)
+}
+
+unsafe fn solve_helper(handle: &RelaxedIKHandle, pos_arr: *const c_double, pos_length: c_int,
+    quat_arr: *const c_double, quat_length: c_int) -> Result<Vec<f64>, FfiError> {
+    // held for the whole solve so another thread can't swap goals or obstacles underneath it
+    let mut r = lock(handle)?;
+    let g = goals_helper(r.vars.robot.num_chains, pos_arr, pos_length, quat_arr, quat_length)?;
+
+    let x = r.solve(&g)?.x;
     // println!("{:?}", x);
-    
-    x
+
+    Ok(x)
 }
@@ -15,6 +15,9 @@ pub struct Arm {
     pub out_positions: Vec<nalgebra::Vector3<f64>>,
     pub out_rot_mats: Vec<nalgebra::Matrix3<f64>>,
     pub out_rot_quats: Vec<nalgebra::UnitQuaternion<f64>>,
+    // tool center point in the frame of the last joint, identity by default. everything reported as the end effector
+    // pose (and its jacobian) is this point, the frames themselves end at the last joint
+    pub tcp: nalgebra::Isometry3<f64>,
     __do_rot_offset: Vec<bool>,
     __is_prismatic: Vec<bool>,
     __is_revolute_or_continuous: Vec<bool>,
@@ -131,7 +134,7 @@ impl Arm{
 
 
         Arm{axis_types, displacements, disp_offset, pos_offsets, rot_offsets, rot_offset_matrices, rot_offset_quats,
-            joint_types, num_dof, out_positions, out_rot_mats, out_rot_quats, __do_rot_offset, __is_prismatic,
+            joint_types, num_dof, out_positions, out_rot_mats, out_rot_quats, tcp: nalgebra::Isometry3::identity(), __do_rot_offset, __is_prismatic,
             __is_revolute_or_continuous, __is_fixed, __is_x, __is_y, __is_z, __is_neg_x, __is_neg_y,
             __is_neg_z, __aux_matrix}
     }
@@ -288,25 +291,116 @@ impl Arm{
             }
         }
 
-        (pt, rot_quat)
+        (pt + rot_quat * self.tcp.translation.vector, rot_quat * self.tcp.rotation)
+    }
+
+    // from the output of get_frames_immutable, one per displacement: the pose of the link each joint moves, at the joint
+    // (the joint's child frame in urdf terms). the last frame of get_frames_immutable is the end effector link
+    pub fn link_poses_from_frames(&self, frames: &(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)) -> Vec<nalgebra::Isometry3<f64>> {
+        let mut out: Vec<nalgebra::Isometry3<f64>> = Vec::new();
+        for i in 0..self.displacements.len() {
+            // frame i+1 is the link's frame moved along displacements[i] and turned by the next joint's rotation offset
+            let rot_quat = frames.1[i + 1] * self.rot_offset_quats[i + 1].inverse();
+            let pt = frames.0[i + 1] - rot_quat * self.displacements[i];
+            out.push(nalgebra::Isometry3::from_parts(nalgebra::Translation3::from(pt), rot_quat));
+        }
+        out
+    }
+
+    // the end effector pose (with the tcp) from the output of get_frames_immutable
+    pub fn ee_pose_from_frames(&self, frames: &(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)) -> (nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>) {
+        let pt = frames.0[frames.0.len() - 1];
+        let rot_quat = frames.1[frames.1.len() - 1];
+        (pt + rot_quat * self.tcp.translation.vector, rot_quat * self.tcp.rotation)
+    }
+
+    // geometric jacobian of the end effector, 6 x num_dof (rows are linear xyz then angular xyz), from the output of get_frames_immutable
+    pub fn get_jacobian_immutable(&self, frames: &(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)) -> nalgebra::DMatrix<f64> {
+        let mut jacobian = nalgebra::DMatrix::zeros(6, self.num_dof);
+        let ee_position = self.ee_pose_from_frames(frames).0;
+
+        // prismatic offsets are applied unrotated in get_frames_immutable, so joints upstream of them don't swing them around
+        let mut downstream_prismatic: Vec<nalgebra::Vector3<f64>> = vec![nalgebra::Vector3::zeros(); self.displacements.len() + 1];
+        for i in (0..self.displacements.len()).rev() {
+            downstream_prismatic[i] = downstream_prismatic[i + 1];
+            if self.__is_prismatic[i] {
+                downstream_prismatic[i] += frames.0[i + 1] - frames.0[i] - frames.1[i] * self.displacements[i];
+            }
+        }
+
+        let mut joint_idx: usize = 0;
+        for i in 0..self.displacements.len() {
+            if !self.__is_revolute_or_continuous[i] && !self.__is_prismatic[i] {
+                continue;
+            }
+
+            let axis = self.get_joint_axis(joint_idx);
+            if self.__is_revolute_or_continuous[i] {
+                // the joint rotates about its axis through frame i
+                let world_axis = frames.1[i] * axis;
+                let linear = world_axis.cross(&(ee_position - frames.0[i] - downstream_prismatic[i]));
+                for r in 0..3 {
+                    jacobian[(r, joint_idx)] = linear[r];
+                    jacobian[(r + 3, joint_idx)] = world_axis[r];
+                }
+            } else {
+                for r in 0..3 {
+                    jacobian[(r, joint_idx)] = axis[r];
+                }
+            }
+            joint_idx += 1;
+        }
+
+        jacobian
+    }
+
+    pub fn jacobian(&self, x: &[f64]) -> nalgebra::DMatrix<f64> {
+        let frames = self.get_frames_immutable(x);
+        self.get_jacobian_immutable(&frames)
+    }
+
+    pub fn manipulability(&self, x: &[f64]) -> f64 {
+        yoshikawa_manipulability(&self.jacobian(x))
+    }
+
+    pub fn condition_number(&self, x: &[f64]) -> f64 {
+        jacobian_condition_number(&self.jacobian(x))
+    }
+
+    pub fn get_joint_axis(&self, joint_idx: usize) -> nalgebra::Vector3<f64> {
+        if self.__is_x[joint_idx] {
+            nalgebra::Vector3::new(1., 0., 0.)
+        } else if self.__is_y[joint_idx] {
+            nalgebra::Vector3::new(0., 1., 0.)
+        } else if self.__is_z[joint_idx] {
+            nalgebra::Vector3::new(0., 0., 1.)
+        } else if self.__is_neg_x[joint_idx] {
+            nalgebra::Vector3::new(-1., 0., 0.)
+        } else if self.__is_neg_y[joint_idx] {
+            nalgebra::Vector3::new(0., -1., 0.)
+        } else if self.__is_neg_z[joint_idx] {
+            nalgebra::Vector3::new(0., 0., -1.)
+        } else {
+            nalgebra::Vector3::zeros()
+        }
     }
 
     pub fn get_ee_position(&mut self, x: &[f64]) -> nalgebra::Vector3<f64> {
         self.get_frames(x);
         let last_index = self.out_positions.len() - 1;
-        self.out_positions[last_index].clone()
+        self.out_positions[last_index] + self.out_rot_mats[last_index] * self.tcp.translation.vector
     }
 
     pub fn get_ee_rot_mat(&mut self, x: &[f64]) -> nalgebra::Matrix3<f64> {
         self.get_frames(x);
         let last_index = self.out_rot_mats.len() - 1;
-        self.out_rot_mats[last_index].clone()
+        self.out_rot_mats[last_index] * self.tcp.rotation.to_rotation_matrix().into_inner()
     }
 
     pub fn get_ee_quat(&mut self, x: &[f64]) -> nalgebra::UnitQuaternion<f64> {
         self.get_frames(x);
         let last_index = self.out_rot_quats.len() - 1;
-        self.out_rot_quats[last_index].clone()
+        self.out_rot_quats[last_index] * self.tcp.rotation
     }
 
     fn __update_frames(&mut self, i: usize, joint_val: f64, __do_rot_offset: bool, __is_prismatic: bool,
@@ -526,6 +620,27 @@ pub fn euler_triple_to_3x3(t: &Vec<f64>) -> nalgebra::Matrix3<f64>{
     zy*xm
 }
 
+// sqrt(det(J J^T)), computed as the product of the singular values so that chains with fewer than 6 dof don't always read 0
+pub fn yoshikawa_manipulability(jacobian: &nalgebra::DMatrix<f64>) -> f64 {
+    let singular_values = jacobian.clone().singular_values();
+    let mut out = 1.0;
+    for i in 0..singular_values.len() {
+        out *= singular_values[i];
+    }
+    out
+}
+
+// ratio of the largest to the smallest singular value, infinite at a singularity
+pub fn jacobian_condition_number(jacobian: &nalgebra::DMatrix<f64>) -> f64 {
+    let singular_values = jacobian.clone().singular_values();
+    let max = singular_values.max();
+    let min = singular_values.min();
+    if min <= 0.0 {
+        return std::f64::INFINITY;
+    }
+    max / min
+}
+
 /*
 pub fn get_frame_closure(axis: &String, joint_type: &String, rot_offset_matrix: &nalgebra::Matrix3<f64>, disp: &nalgebra::Vector3<f64>, do_rot_offset: bool) -> impl Fn(f64) -> (nalgebra::Matrix3<f64>, nalgebra::Vector3<f64>) {
     let m: nalgebra::Matrix3<f64> = nalgebra::Matrix3::identity();
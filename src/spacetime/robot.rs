@@ -1,5 +1,22 @@
 use crate::spacetime::arm;
-use crate::utils_rust::{geometry_utils, yaml_utils};
+use crate::utils_rust::{geometry_utils, yaml_utils, urdf_utils};
+use crate::utils_rust::error_utils::RelaxedIKError;
+use nalgebra::{Isometry3, Translation3};
+
+// poses of one chain's links at one configuration, in the robot's base frame, see Robot::link_poses
+#[derive(Clone, Debug)]
+pub struct ChainPoses {
+    // the link each joint of joint_names[chain] moves, at the joint, in chain order
+    pub joints: Vec<(String, Isometry3<f64>)>,
+    // the end effector link, and the tool center point on it
+    pub ee_link: Isometry3<f64>,
+    pub tcp: Isometry3<f64>
+}
+impl ChainPoses {
+    pub fn joint(&self, name: &str) -> Option<Isometry3<f64>> {
+        self.joints.iter().find(|(n, _)| n == name).map(|(_, pose)| *pose)
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Robot {
@@ -19,16 +36,65 @@ pub struct Robot {
 impl Robot {
     pub fn from_info_file_parser(ifp: &yaml_utils::InfoFileParser) -> Robot {
         let num_chains = ifp.axis_types.len();
-        let num_dof = ifp.velocity_limits.len();
 
         let mut arms: Vec<arm::Arm> = Vec::new();
         for i in 0..num_chains {
-            let a = arm::Arm::new(ifp.axis_types[i].clone(), ifp.displacements[i].clone(),
+            let mut a = arm::Arm::new(ifp.axis_types[i].clone(), ifp.displacements[i].clone(),
                               ifp.disp_offsets[i].clone(), ifp.rot_offsets[i].clone(), ifp.joint_types[i].clone());
+            a.tcp = ifp.tcp_offsets[i];
             arms.push(a);
         }
 
-        let subchain_indices = Robot::get_subchain_indices(&ifp.joint_names, &ifp.joint_ordering);
+        Robot::from_arms(arms, ifp.joint_names.clone(), ifp.joint_ordering.clone(), ifp.joint_limits.clone(), ifp.velocity_limits.clone())
+    }
+
+    pub fn from_urdf(urdf: &urdf_utils::UrdfParser, base_links: &Vec<String>, ee_links: &Vec<String>) -> Result<Robot, RelaxedIKError> {
+        // a single base link is shared by every chain
+        if base_links.len() != 1 && base_links.len() != ee_links.len() {
+            return Err(RelaxedIKError::Urdf{path: urdf.file_path.clone(), message: format!("need one base link, or one per end effector link, got {} base links for {} end effector links",
+                base_links.len(), ee_links.len())});
+        }
+
+        let mut arms: Vec<arm::Arm> = Vec::new();
+        let mut joint_names: Vec<Vec<String>> = Vec::new();
+        let mut joint_ordering: Vec<String> = Vec::new();
+        let mut joint_limits: Vec<[f64; 2]> = Vec::new();
+        let mut velocity_limits: Vec<f64> = Vec::new();
+        for i in 0..ee_links.len() {
+            let base_link = if base_links.len() == 1 { &base_links[0] } else { &base_links[i] };
+            let chain = urdf.get_chain(base_link.as_str(), ee_links[i].as_str())?;
+            arms.push(arm::Arm::new(chain.axis_types.clone(), chain.displacements.clone(), chain.disp_offset.clone(),
+                                    chain.rot_offsets.clone(), chain.joint_types.clone()));
+            // the limits are only there for the movable joints
+            let mut movable_idx = 0;
+            for j in 0..chain.joint_names.len() {
+                if chain.joint_types[j] == "fixed" {
+                    continue;
+                }
+                if !joint_ordering.contains(&chain.joint_names[j]) {
+                    joint_ordering.push(chain.joint_names[j].clone());
+                    joint_limits.push(chain.joint_limits[movable_idx]);
+                    velocity_limits.push(chain.velocity_limits[movable_idx]);
+                }
+                movable_idx += 1;
+            }
+            joint_names.push(chain.joint_names);
+        }
+
+        Ok(Robot::from_arms(arms, joint_names, joint_ordering, joint_limits, velocity_limits))
+    }
+
+    pub fn from_urdf_path(fp: String, base_links: &Vec<String>, ee_links: &Vec<String>) -> Result<Robot, RelaxedIKError> {
+        let urdf = urdf_utils::UrdfParser::from_urdf_path(fp)?;
+        Robot::from_urdf(&urdf, base_links, ee_links)
+    }
+
+    pub fn from_arms(arms: Vec<arm::Arm>, joint_names: Vec<Vec<String>>, joint_ordering: Vec<String>,
+                     joint_limits: Vec<[f64; 2]>, velocity_limits: Vec<f64>) -> Robot {
+        let num_chains = arms.len();
+        let num_dof = velocity_limits.len();
+
+        let subchain_indices = Robot::get_subchain_indices(&joint_names, &joint_ordering);
 
         let mut __subchain_outputs: Vec<Vec<f64>> = Vec::new();
         for i in 0..subchain_indices.len() {
@@ -41,18 +107,17 @@ impl Robot {
 
         let mut upper_bounds: Vec<f64> = Vec::new();
         let mut lower_bounds: Vec<f64> = Vec::new();
-        for i in 0..ifp.joint_limits.len() {
-            upper_bounds.push(ifp.joint_limits[i][1].clone());
-            lower_bounds.push(ifp.joint_limits[i][0].clone());
+        for i in 0..joint_limits.len() {
+            upper_bounds.push(joint_limits[i][1].clone());
+            lower_bounds.push(joint_limits[i][0].clone());
         }
 
-        Robot{arms, joint_names: ifp.joint_names.clone(), joint_ordering: ifp.joint_ordering.clone(),
-            num_chains, num_dof, subchain_indices, bounds: ifp.joint_limits.clone(), lower_bounds, upper_bounds, velocity_limits: ifp.velocity_limits.clone(), __subchain_outputs}
+        Robot{arms, joint_names, joint_ordering, num_chains, num_dof, subchain_indices, bounds: joint_limits, lower_bounds, upper_bounds, velocity_limits, __subchain_outputs}
     }
 
-    pub fn from_yaml_path(fp: String) -> Robot {
-        let ifp = yaml_utils::InfoFileParser::from_yaml_path(fp);
-        Robot::from_info_file_parser(&ifp)
+    pub fn from_yaml_path(fp: String) -> Result<Robot, RelaxedIKError> {
+        let ifp = yaml_utils::InfoFileParser::from_yaml_path(fp)?;
+        Ok(Robot::from_info_file_parser(&ifp))
     }
 
     pub fn split_into_subchains(&self, x: &[f64]) -> Vec<Vec<f64>>{
@@ -103,6 +168,45 @@ impl Robot {
         out
     }
 
+    // the names link_poses gives each chain's links, one per displacement. info files and urdf chains name the fixed
+    // joints too, a robot put together from arms by hand might not
+    pub fn link_names(&self) -> Result<Vec<Vec<String>>, RelaxedIKError> {
+        for i in 0..self.num_chains {
+            if self.joint_names[i].len() != self.arms[i].displacements.len() {
+                return Err(RelaxedIKError::InvalidArgument{message: format!("chain {} has {} joint names for {} displacements, link poses need one per displacement",
+                    i, self.joint_names[i].len(), self.arms[i].displacements.len())});
+            }
+        }
+        Ok(self.joint_names.clone())
+    }
+
+    // one per chain. a joint shared between chains shows up in each of them
+    pub fn link_poses(&self, x: &[f64]) -> Result<Vec<ChainPoses>, RelaxedIKError> {
+        let link_names = self.link_names()?;
+        let mut out: Vec<ChainPoses> = Vec::new();
+        let frames = self.get_frames_immutable(x);
+        for i in 0..self.num_chains {
+            let link_poses = self.arms[i].link_poses_from_frames(&frames[i]);
+            let mut joints: Vec<(String, Isometry3<f64>)> = Vec::new();
+            for j in 0..link_poses.len() {
+                joints.push((link_names[i][j].clone(), link_poses[j]));
+            }
+            let last_elem = frames[i].0.len() - 1;
+            let ee_link = Isometry3::from_parts(Translation3::from(frames[i].0[last_elem]), frames[i].1[last_elem]);
+            out.push(ChainPoses{joints, ee_link, tcp: ee_link * self.arms[i].tcp});
+        }
+        Ok(out)
+    }
+
+    pub fn link_poses_batch(&self, xs: &[Vec<f64>]) -> Result<Vec<Vec<ChainPoses>>, RelaxedIKError> {
+        xs.iter().map(|x| self.link_poses(x.as_slice())).collect()
+    }
+
+    // the link moved by the joint called name, from the first chain that has it
+    pub fn link_pose(&self, x: &[f64], name: &str) -> Result<Option<Isometry3<f64>>, RelaxedIKError> {
+        Ok(self.link_poses(x)?.iter().filter_map(|c| c.joint(name)).next())
+    }
+
     pub fn get_ee_positions(&mut self, x: &[f64]) -> Vec<nalgebra::Vector3<f64>> {
         let mut out: Vec<nalgebra::Vector3<f64>> = Vec::new();
         self.split_into_subchains_inplace(x);
@@ -130,6 +234,35 @@ impl Robot {
         out
     }
 
+    // end effector jacobian of one chain w.r.t. the full joint vector, 6 x num_dof with zero columns for joints outside the chain
+    pub fn get_jacobian_immutable(&self, frames: &Vec<(Vec<nalgebra::Vector3<f64>>, Vec<nalgebra::UnitQuaternion<f64>>)>, chain_idx: usize) -> nalgebra::DMatrix<f64> {
+        let arm_jacobian = self.arms[chain_idx].get_jacobian_immutable(&frames[chain_idx]);
+        let mut jacobian = nalgebra::DMatrix::zeros(6, self.num_dof);
+        for j in 0..self.subchain_indices[chain_idx].len() {
+            let col = self.subchain_indices[chain_idx][j];
+            for r in 0..6 {
+                jacobian[(r, col)] += arm_jacobian[(r, j)];
+            }
+        }
+        jacobian
+    }
+
+    // end effector jacobian of one chain w.r.t. the full joint vector x
+    pub fn jacobian(&self, x: &[f64], chain: usize) -> nalgebra::DMatrix<f64> {
+        let frames = self.get_frames_immutable(x);
+        self.get_jacobian_immutable(&frames, chain)
+    }
+
+    pub fn manipulability(&self, x: &[f64], chain: usize) -> f64 {
+        let subchains = self.split_into_subchains(x);
+        self.arms[chain].manipulability(subchains[chain].as_slice())
+    }
+
+    pub fn condition_number(&self, x: &[f64], chain: usize) -> f64 {
+        let subchains = self.split_into_subchains(x);
+        self.arms[chain].condition_number(subchains[chain].as_slice())
+    }
+
     fn get_subchain_indices(joint_names: &Vec<Vec<String>>, joint_ordering: &Vec<String>) -> Vec<Vec<usize>> {
         let mut out: Vec<Vec<usize>> = Vec::new();
 
@@ -162,3 +295,20 @@ impl Robot {
 
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::spacetime::robot::Robot;
+
+    // a urdf-built robot names its fixed joints too, so every displacement has a link to pose
+    #[test]
+    fn urdf_robot_has_a_link_name_per_displacement() {
+        let mut robot = Robot::from_urdf_path(format!("{}/config/urdfs/panda_arm_hand.urdf", env!("CARGO_MANIFEST_DIR")), &vec!["panda_link0".to_string()], &vec!["panda_hand".to_string()]).unwrap();
+        let x = vec![0.1; robot.num_dof];
+        let poses = robot.link_poses(&x).unwrap();
+        assert_eq!(poses[0].joints.len(), robot.arms[0].displacements.len());
+        assert!(poses[0].joint("panda_joint8").is_some());
+
+        robot.joint_names[0].pop();
+        assert!(robot.link_poses(&x).is_err());
+    }
+}
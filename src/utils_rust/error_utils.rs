@@ -0,0 +1,50 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum RelaxedIKError {
+    // a file could not be opened or read
+    Io { path: String, source: io::Error },
+    // a file was read, but is not valid yaml / xml
+    Parse { path: String, message: String },
+    // a required key (or list index) is missing from a config file
+    MissingKey { path: String, key: String },
+    // a key is present, but its value has the wrong type or shape
+    InvalidValue { path: String, key: String, expected: String },
+    // the urdf describes something the kinematic model can't represent
+    Urdf { path: String, message: String },
+    // an obstacle could not be turned into a collision shape
+    InvalidObstacle { name: String, message: String },
+    // the goals passed to a solve call don't match the robot
+    InvalidGoals { message: String },
+    // some other argument (a joint vector, a mode name) doesn't match the robot or isn't supported
+    InvalidArgument { message: String },
+    // the optimizer hit a non-finite cost or gradient
+    Solver { message: String }
+}
+
+impl fmt::Display for RelaxedIKError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RelaxedIKError::Io { path, source } => write!(f, "could not read {}: {}", path, source),
+            RelaxedIKError::Parse { path, message } => write!(f, "could not parse {}: {}", path, message),
+            RelaxedIKError::MissingKey { path, key } => write!(f, "{}: missing key {}", path, key),
+            RelaxedIKError::InvalidValue { path, key, expected } => write!(f, "{}: key {} should be {}", path, key, expected),
+            RelaxedIKError::Urdf { path, message } => write!(f, "{}: {}", path, message),
+            RelaxedIKError::InvalidObstacle { name, message } => write!(f, "obstacle {}: {}", name, message),
+            RelaxedIKError::InvalidGoals { message } => write!(f, "invalid goals: {}", message),
+            RelaxedIKError::InvalidArgument { message } => write!(f, "invalid argument: {}", message),
+            RelaxedIKError::Solver { message } => write!(f, "solver failed: {}", message)
+        }
+    }
+}
+
+impl Error for RelaxedIKError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RelaxedIKError::Io { source, .. } => Some(source),
+            _ => None
+        }
+    }
+}
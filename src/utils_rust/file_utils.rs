@@ -2,8 +2,10 @@ use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 use std::fs::read_dir;
+use std::path::Path;
 
 use path_slash::PathBufExt;
+use crate::utils_rust::error_utils::RelaxedIKError;
 
 pub fn get_path_to_src() -> String {
     let path = env::current_dir().unwrap();
@@ -13,19 +15,91 @@ pub fn get_path_to_src() -> String {
     path_to_src
 }
 
-pub fn get_file_contents(fp: String) -> String {
-    let mut file = File::open(fp.as_str()).unwrap();
+// where the solver finds its config tree (settings.yaml, info_files, urdfs, collision_nn_rust, collision_files, animation_files)
+// and the point cloud files referenced from settings.yaml. from_env falls back to the old layout next to
+// the working directory, unless RELAXED_IK_CONFIG_DIR / RELAXED_IK_GEOMETRY_DIR are set
+#[derive(Clone, Debug)]
+pub struct ConfigPaths {
+    pub config_dir: String,
+    pub geometry_dir: String
+}
+impl ConfigPaths {
+    pub fn new(config_dir: String, geometry_dir: String) -> Self {
+        Self{config_dir, geometry_dir}
+    }
+
+    pub fn from_env() -> Self {
+        let config_dir = match env::var("RELAXED_IK_CONFIG_DIR") {
+            Ok(d) => d,
+            Err(_) => get_path_to_src() + "relaxed_ik_core/config/"
+        };
+        let geometry_dir = match env::var("RELAXED_IK_GEOMETRY_DIR") {
+            Ok(d) => d,
+            Err(_) => get_path_to_src() + "geometry_files/"
+        };
+        Self{config_dir, geometry_dir}
+    }
+
+    pub fn settings(&self) -> String {
+        join_path(&self.config_dir, "settings.yaml")
+    }
+
+    pub fn info_file(&self, info_file_name: &str) -> String {
+        join_path(&self.config_dir, format!("info_files/{}", info_file_name).as_str())
+    }
+
+    pub fn urdf(&self, urdf_file_name: &str) -> String {
+        join_path(&self.config_dir, format!("urdfs/{}", urdf_file_name).as_str())
+    }
+
+    // info files name the network without its extension
+    pub fn collision_nn(&self, collision_nn_file: &str) -> String {
+        join_path(&self.config_dir, format!("collision_nn_rust/{}.yaml", collision_nn_file).as_str())
+    }
+
+    pub fn collision_file(&self, collision_file_name: &str) -> String {
+        join_path(&self.config_dir, format!("collision_files/{}", collision_file_name).as_str())
+    }
+
+    // cartesian paths named by input_device in settings.yaml, and obstacle animations. settings.yaml may leave
+    // off the extension, then the first of .yaml, .yml and .csv that exists is used
+    pub fn animation_file(&self, animation_file_name: &str) -> String {
+        let fp = join_path(&self.config_dir, format!("animation_files/{}", animation_file_name).as_str());
+        if Path::new(fp.as_str()).extension().is_some() {
+            return fp;
+        }
+        for extension in ["yaml", "yml", "csv"].iter() {
+            let candidate = format!("{}.{}", fp, extension);
+            if Path::new(candidate.as_str()).is_file() {
+                return candidate;
+            }
+        }
+        format!("{}.yaml", fp)
+    }
+
+    pub fn geometry_file(&self, file_name: &str) -> String {
+        join_path(&self.geometry_dir, file_name)
+    }
+}
+
+fn join_path(dir: &str, rest: &str) -> String {
+    Path::new(dir).join(rest).to_string_lossy().into_owned()
+}
+
+pub fn get_file_contents(fp: String) -> Result<String, RelaxedIKError> {
+    let mut file = File::open(fp.as_str()).map_err(|e| RelaxedIKError::Io{path: fp.clone(), source: e})?;
     let mut contents = String::new();
-    let res = file.read_to_string(&mut contents).unwrap();
-    contents
+    file.read_to_string(&mut contents).map_err(|e| RelaxedIKError::Io{path: fp.clone(), source: e})?;
+    Ok(contents)
 }
 
 
-pub fn get_all_files_in_directory(fp: String) -> Vec<String> {
+pub fn get_all_files_in_directory(fp: String) -> Result<Vec<String>, RelaxedIKError> {
     let mut out: Vec<String> = Vec::new();
-    let it = read_dir(fp);
-    for i in it.unwrap() {
-        out.push(i.unwrap().file_name().into_string().unwrap());
+    let it = read_dir(fp.as_str()).map_err(|e| RelaxedIKError::Io{path: fp.clone(), source: e})?;
+    for i in it {
+        let entry = i.map_err(|e| RelaxedIKError::Io{path: fp.clone(), source: e})?;
+        out.push(entry.file_name().to_string_lossy().into_owned());
     }
-    out
+    Ok(out)
 }
\ No newline at end of file
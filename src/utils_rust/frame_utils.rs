@@ -0,0 +1,139 @@
+use nalgebra::Isometry3;
+use crate::utils_rust::error_utils::RelaxedIKError;
+
+// a fixed frame added with FrameTree::add_frame, transform is its pose in the parent frame
+#[derive(Clone, Debug)]
+pub struct NamedFrame {
+    pub name: String,
+    pub parent: String,
+    pub transform: Isometry3<f64>
+}
+
+// the frames goals can be given in. built in are "world", the robot's base frame ("base", or the info file's
+// fixed_frame) and one frame per end effector (its ee_fixed_joint, or ee_<chain index>). any other name is taken
+// to be an obstacle, and fixed frames can be hung off all of these with add_frame.
+// the tree only holds the fixed parts, end effector and obstacle poses are looked up by RelaxedIK
+#[derive(Clone, Debug)]
+pub struct FrameTree {
+    pub base_frame: String,
+    pub ee_frames: Vec<String>,
+    // where the base frame sits in the world frame, identity by default
+    pub base_in_world: Isometry3<f64>,
+    pub frames: Vec<NamedFrame>
+}
+impl FrameTree {
+    pub fn new(base_frame: String, ee_frames: Vec<String>) -> Self {
+        Self{base_frame, ee_frames, base_in_world: Isometry3::identity(), frames: Vec::new()}
+    }
+
+    // frames named after the chain indices only
+    pub fn with_num_chains(num_chains: usize) -> Self {
+        let mut ee_frames: Vec<String> = Vec::new();
+        for i in 0..num_chains {
+            ee_frames.push(format!("ee_{}", i));
+        }
+        FrameTree::new("base".to_string(), ee_frames)
+    }
+
+    pub fn is_base(&self, name: &str) -> bool {
+        name == "base" || name == self.base_frame
+    }
+
+    pub fn is_world(&self, name: &str) -> bool {
+        name == "world" && !self.is_base(name)
+    }
+
+    pub fn chain_of(&self, name: &str) -> Option<usize> {
+        if let Some(i) = self.ee_frames.iter().position(|f| f == name) {
+            return Some(i);
+        }
+        if name.starts_with("ee_") {
+            if let Ok(i) = name[3..].parse::<usize>() {
+                if i < self.ee_frames.len() {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn is_built_in(&self, name: &str) -> bool {
+        self.is_base(name) || name == "world" || self.chain_of(name).is_some()
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.frames.iter().position(|f| f.name == name)
+    }
+
+    pub fn set_base_pose(&mut self, base_in_world: Isometry3<f64>) -> Result<(), RelaxedIKError> {
+        if self.is_base("world") {
+            return Err(RelaxedIKError::InvalidArgument{message: "the base frame is the world frame of this robot".to_string()});
+        }
+        self.base_in_world = base_in_world;
+        Ok(())
+    }
+
+    // parent can be any frame name, including obstacles that don't exist yet
+    pub fn add_frame(&mut self, name: &str, parent: &str, transform: Isometry3<f64>) -> Result<(), RelaxedIKError> {
+        if self.is_built_in(name) || self.find(name).is_some() {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("a frame named {} already exists", name)});
+        }
+        let mut ancestor = parent.to_string();
+        loop {
+            if ancestor == name {
+                return Err(RelaxedIKError::InvalidArgument{message: format!("frame {} would be its own ancestor", name)});
+            }
+            match self.find(ancestor.as_str()) {
+                Some(i) => ancestor = self.frames[i].parent.clone(),
+                None => break
+            }
+        }
+        self.frames.push(NamedFrame{name: name.to_string(), parent: parent.to_string(), transform});
+        Ok(())
+    }
+
+    pub fn set_frame_transform(&mut self, name: &str, transform: Isometry3<f64>) -> Result<(), RelaxedIKError> {
+        match self.find(name) {
+            Some(i) => {
+                self.frames[i].transform = transform;
+                Ok(())
+            },
+            None => Err(RelaxedIKError::InvalidArgument{message: format!("no added frame named {}", name)})
+        }
+    }
+
+    pub fn remove_frame(&mut self, name: &str) -> Result<(), RelaxedIKError> {
+        let i = match self.find(name) {
+            Some(i) => i,
+            None => return Err(RelaxedIKError::InvalidArgument{message: format!("no added frame named {}", name)})
+        };
+        if self.frames.iter().any(|f| f.parent == name) {
+            return Err(RelaxedIKError::InvalidArgument{message: format!("frame {} still has child frames", name)});
+        }
+        self.frames.remove(i);
+        Ok(())
+    }
+
+    // the first ancestor of name that isn't an added frame (so world, base, an end effector or an obstacle),
+    // and the pose of name in that frame. for those frames themselves, the frame and identity
+    pub fn root(&self, name: &str) -> (String, Isometry3<f64>) {
+        let mut root = name.to_string();
+        let mut pose = Isometry3::identity();
+        while let Some(i) = self.find(root.as_str()) {
+            pose = self.frames[i].transform * pose;
+            root = self.frames[i].parent.clone();
+        }
+        (root, pose)
+    }
+
+    // poses in the base frame of the frames that don't depend on the robot or the obstacles
+    pub fn fixed_pose_in_base(&self, name: &str) -> Option<Isometry3<f64>> {
+        if self.is_base(name) {
+            Some(Isometry3::identity())
+        } else if self.is_world(name) {
+            Some(self.base_in_world.inverse())
+        } else {
+            None
+        }
+    }
+}
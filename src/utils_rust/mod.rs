@@ -7,4 +7,8 @@ pub mod sampler;
 pub mod self_collision_engine;
 pub mod collision_object;
 pub mod shape_parser_utils;
-pub mod robot_shape_model;
\ No newline at end of file
+pub mod robot_shape_model;
+pub mod urdf_utils;
+pub mod error_utils;
+pub mod trajectory_utils;
+pub mod frame_utils;
\ No newline at end of file
@@ -1,7 +1,6 @@
 use crate::utils_rust::robot_shape_model::RobotShapeModel;
-use crate::spacetime::robot::Robot;
-use crate::utils_rust::yaml_utils::{RobotCollisionSpecFileParser, InfoFileParser};
-use crate::utils_rust::file_utils::get_path_to_src;
+use crate::utils_rust::file_utils::ConfigPaths;
+use crate::utils_rust::error_utils::RelaxedIKError;
 use crate::utils_rust::collision_object::CollisionObject;
 use crate::utils_rust::transformations;
 use crate::utils_rust::sampler::{ThreadRobotSampler, ThreadSampler};
@@ -35,22 +34,19 @@ impl SelfCollisionEngine {
         sce
     }
 
-    pub fn from_yaml_path(fp: String) -> Self {
+    pub fn from_yaml_path(fp: String) -> Result<Self, RelaxedIKError> {
+        SelfCollisionEngine::from_yaml_path_with_paths(fp, &ConfigPaths::from_env())
+    }
+
+    pub fn from_yaml_path_with_paths(fp: String, paths: &ConfigPaths) -> Result<Self, RelaxedIKError> {
         // yaml path to info file
-        let robot = Robot::from_yaml_path(fp.clone());
-        let ifp = InfoFileParser::from_yaml_path(fp.clone());
-        let collision_file_name = ifp.collision_file_name;
-        let fp2 = get_path_to_src() + "RelaxedIK/Config/collision_files_rust/" + collision_file_name.as_str();
-        let robot_collision_specs_file = RobotCollisionSpecFileParser::from_yaml_path(fp2.clone());
-        let robot_shape_model = RobotShapeModel::from_robot_and_specs(&robot, &robot_collision_specs_file, &ifp.starting_config);
-        SelfCollisionEngine::from_robot_shape_model(robot_shape_model)
+        let robot_shape_model = RobotShapeModel::from_yaml_path_with_paths(fp, paths)?;
+        Ok(SelfCollisionEngine::from_robot_shape_model(robot_shape_model))
     }
 
-    pub fn from_info_file_name(info_file_name: String) -> Self {
-        let path_to_src = get_path_to_src();
-        let fp = path_to_src + "RelaxedIK/Config/info_files/" + info_file_name.as_str();
-        let robot_shape_model = RobotShapeModel::from_yaml_path(fp);
-        SelfCollisionEngine::from_robot_shape_model(robot_shape_model)
+    pub fn from_info_file_name(info_file_name: String) -> Result<Self, RelaxedIKError> {
+        let paths = ConfigPaths::from_env();
+        SelfCollisionEngine::from_yaml_path_with_paths(paths.info_file(info_file_name.as_str()), &paths)
      }
 
     pub fn calibrate_allowed_collision_matrix(&mut self, num_samples: usize) {
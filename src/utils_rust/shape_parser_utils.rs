@@ -1,3 +1,6 @@
+use crate::utils_rust::trajectory_utils::CartesianTrajectory;
+use crate::utils_rust::error_utils::RelaxedIKError;
+
 
 #[derive(Clone, Debug)]
 pub struct Cuboid {
@@ -88,4 +91,32 @@ impl PCEnv {
     pub fn new(name: String, rx: f64, ry: f64, rz: f64, tx: f64, ty: f64, tz: f64, is_dynamic: bool, points: Vec<SphereEnv>) -> Self {
         Self {name, rx, ry, rz, tx, ty, tz, is_dynamic, points}
     }
+}
+
+// moves the obstacle called name along trajectory, as offsets from the pose it was given in settings.yaml.
+// the animation loops, so it can be advanced by any time
+#[derive(Clone, Debug)]
+pub struct ObstacleAnimation {
+    pub name: String,
+    pub trajectory: CartesianTrajectory
+}
+impl ObstacleAnimation {
+    pub fn new(name: String, trajectory: CartesianTrajectory) -> Result<Self, RelaxedIKError> {
+        if trajectory.num_chains() != 1 {
+            return Err(RelaxedIKError::InvalidObstacle{name, message: format!("an obstacle animation needs one pose per waypoint, got {}", trajectory.num_chains())});
+        }
+        Ok(Self{name, trajectory})
+    }
+
+    // (translation offset, rotation offset) at time t
+    pub fn offset_at(&self, t: f64) -> (nalgebra::Vector3<f64>, nalgebra::UnitQuaternion<f64>) {
+        let start = self.trajectory.start_time();
+        let duration = self.trajectory.end_time() - start;
+        let mut local_t = start;
+        if duration > 0.0 {
+            local_t = start + (t - start).rem_euclid(duration);
+        }
+        let sample = self.trajectory.sample(local_t);
+        (sample.pos_goals[0], sample.quat_goals[0])
+    }
 }
\ No newline at end of file
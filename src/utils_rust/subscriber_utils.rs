@@ -1,6 +1,6 @@
 use std::sync::{Arc, Mutex};
 use std::thread;
-use nalgebra::{Vector3, Quaternion, UnitQuaternion, Unit};
+use nalgebra::{Vector3, Quaternion, UnitQuaternion, Unit, Isometry3};
 
 pub struct SingleValueSubscriber<T> {
     pub data: T
@@ -18,15 +18,202 @@ let subscriber = rosrust::subscribe("topic", 3, move |v: msg::std_msgs::UInt64|
 });
 */
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RotationMask {
+    // match the whole goal orientation
+    Full,
+    // only line up this axis of the end effector frame with the same axis of the goal frame, rotation about it is free
+    // (e.g. Axis(z) keeps the tool pointing the way the goal does, at any yaw)
+    Axis(Vector3<f64>),
+    // orientation is not part of the goal
+    Free
+}
+
+// which parts of a chain's goal pose the pose objectives try to match
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GoalMask {
+    // weight on the squared position error along each world axis, 0 ignores that axis (e.g. (1, 1, 0) ignores height)
+    pub position: Vector3<f64>,
+    pub rotation: RotationMask
+}
+impl GoalMask {
+    pub fn new(position: Vector3<f64>, rotation: RotationMask) -> Self {Self{position, rotation}}
+
+    pub fn full() -> Self {GoalMask::new(Vector3::new(1.0, 1.0, 1.0), RotationMask::Full)}
+
+    pub fn position_only() -> Self {GoalMask::new(Vector3::new(1.0, 1.0, 1.0), RotationMask::Free)}
+
+    pub fn rotation_only() -> Self {GoalMask::new(Vector3::zeros(), RotationMask::Full)}
+
+    pub fn is_valid(&self) -> bool {
+        let position_ok = self.position.iter().all(|w| w.is_finite() && *w >= 0.0);
+        let rotation_ok = match self.rotation {
+            RotationMask::Axis(a) => a.iter().all(|c| c.is_finite()) && a.norm() > 0.0,
+            _ => true
+        };
+        position_ok && rotation_ok
+    }
+
+    // how far the end effector is outside the goal's tolerance band, and the derivative of that w.r.t. the end effector position
+    pub fn position_error_gradient(&self, tolerance: &GoalTolerance, ee_pos: &Vector3<f64>, goal_pos: &Vector3<f64>) -> (f64, Vector3<f64>) {
+        let diff = ee_pos - goal_pos;
+        match tolerance.position {
+            PositionTolerance::Sphere(radius) => {
+                let n = (self.position[0] * diff[0].powi(2) + self.position[1] * diff[1].powi(2) + self.position[2] * diff[2].powi(2)).sqrt();
+                if n <= radius {
+                    return (0.0, Vector3::zeros());
+                }
+                (n - radius, diff.component_mul(&self.position) / n)
+            },
+            _ => {
+                let mut excess = diff.clone();
+                if let PositionTolerance::Box(half_extents) = tolerance.position {
+                    for k in 0..3 {
+                        excess[k] = diff[k].signum() * (diff[k].abs() - half_extents[k]).max(0.0);
+                    }
+                }
+                let x_val = (self.position[0] * excess[0].powi(2) + self.position[1] * excess[1].powi(2) + self.position[2] * excess[2].powi(2)).sqrt();
+                if x_val <= 0.0 {
+                    return (0.0, Vector3::zeros());
+                }
+                (x_val, excess.component_mul(&self.position) / x_val)
+            }
+        }
+    }
+
+    pub fn position_error(&self, tolerance: &GoalTolerance, ee_pos: &Vector3<f64>, goal_pos: &Vector3<f64>) -> f64 {
+        self.position_error_gradient(tolerance, ee_pos, goal_pos).0
+    }
+
+    // radians outside the goal's tolerance band, and the derivative of that w.r.t. the end effector's angular velocity
+    pub fn rotation_error_gradient(&self, tolerance: &GoalTolerance, ee_quat: &UnitQuaternion<f64>, goal_quat: &UnitQuaternion<f64>) -> (f64, Vector3<f64>) {
+        let (angle, dir) = match self.rotation {
+            RotationMask::Full => {
+                // the error rotation e = q_ee * q_goal^-1 is in the world frame, so its angle changes along axis(e)
+                let mut err = (ee_quat * goal_quat.inverse()).into_inner();
+                if err.w < 0.0 {
+                    err = -err;
+                }
+                let axis_norm = err.vector().norm();
+                if axis_norm <= 0.0 {
+                    return (0.0, Vector3::zeros());
+                }
+                (2.0 * axis_norm.atan2(err.w), err.vector() / axis_norm)
+            },
+            RotationMask::Axis(a) => {
+                // the angle between u = q_ee * a and w = q_goal * a changes along -(u x w) / |u x w|
+                let u = ee_quat * a.normalize();
+                let w = goal_quat * a.normalize();
+                let c = u.cross(&w);
+                let c_norm = c.norm();
+                if c_norm <= 0.0 {
+                    return (c_norm.atan2(u.dot(&w)), Vector3::zeros());
+                }
+                (c_norm.atan2(u.dot(&w)), -c / c_norm)
+            },
+            RotationMask::Free => return (0.0, Vector3::zeros())
+        };
+        if angle <= tolerance.rotation {
+            return (0.0, Vector3::zeros());
+        }
+        (angle - tolerance.rotation, dir)
+    }
+
+    pub fn rotation_error(&self, tolerance: &GoalTolerance, ee_quat: &UnitQuaternion<f64>, goal_quat: &UnitQuaternion<f64>) -> f64 {
+        self.rotation_error_gradient(tolerance, ee_quat, goal_quat).0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PositionTolerance {
+    Exact,
+    // anywhere within this distance of the goal position
+    Sphere(f64),
+    // anywhere within these half extents of the goal position, along the world axes
+    Box(Vector3<f64>)
+}
+
+// a band around a chain's goal pose inside which the pose objectives are at their minimum, leaving the
+// optimizer free to spend the slack on smoothness and collision avoidance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GoalTolerance {
+    pub position: PositionTolerance,
+    // radians the orientation (or, with RotationMask::Axis, the masked axis) may tilt away from the goal, a cone for Axis
+    pub rotation: f64
+}
+impl GoalTolerance {
+    pub fn new(position: PositionTolerance, rotation: f64) -> Self {Self{position, rotation}}
+
+    pub fn exact() -> Self {GoalTolerance::new(PositionTolerance::Exact, 0.0)}
+
+    pub fn is_valid(&self) -> bool {
+        let position_ok = match self.position {
+            PositionTolerance::Exact => true,
+            PositionTolerance::Sphere(r) => r.is_finite() && r >= 0.0,
+            PositionTolerance::Box(h) => h.iter().all(|c| c.is_finite() && *c >= 0.0)
+        };
+        position_ok && self.rotation.is_finite() && self.rotation >= 0.0
+    }
+}
+
+// holds chain's end effector at pose in other_chain's end effector frame, for the match_relative_pose objective
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RelativePoseGoal {
+    pub chain: usize,
+    pub other_chain: usize,
+    pub pose: Isometry3<f64>
+}
+
+// what the goals passed to solve are measured from. the relative frames add the position goal as an offset and apply
+// the rotation goal on top of a reference pose: the end effector's pose at the starting config (RelativeToStart),
+// or at the last solution (RelativeToCurrent, offsets in the world frame, and RelativeToTool, offsets in the end effector frame)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GoalFrame {
+    Absolute,
+    RelativeToStart,
+    RelativeToCurrent,
+    RelativeToTool
+}
+impl GoalFrame {
+    // the names used in settings.yaml
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "absolute" => Some(GoalFrame::Absolute),
+            "relative" => Some(GoalFrame::RelativeToStart),
+            "relative_to_current" => Some(GoalFrame::RelativeToCurrent),
+            "relative_to_tool" => Some(GoalFrame::RelativeToTool),
+            _ => None
+        }
+    }
+
+    pub fn from_relative(relative: bool) -> Self {
+        if relative { GoalFrame::RelativeToStart } else { GoalFrame::Absolute }
+    }
+}
+
 pub struct EEPoseGoalsSubscriber {
     pub pos_goals: Vec<Vector3<f64>>,
-    pub quat_goals: Vec<UnitQuaternion<f64>>
+    pub quat_goals: Vec<UnitQuaternion<f64>>,
+    // optional, one per chain. left empty, the masks from the previous goals (full poses at first) stay in place
+    pub masks: Vec<GoalMask>,
+    // optional, one per chain, same as masks (exact poses at first)
+    pub tolerances: Vec<GoalTolerance>,
+    // optional, override the solver's goal frames for this call only
+    pub position_mode: Option<GoalFrame>,
+    pub rotation_mode: Option<GoalFrame>,
+    // optional, one per chain, the name of the frame each goal is in (see FrameTree), for this call only.
+    // left empty, every goal is in the base frame. goals in any other frame are full poses in that frame,
+    // position_mode and rotation_mode only apply to goals in the base frame
+    pub frames: Vec<String>
 }
 impl EEPoseGoalsSubscriber {
     pub fn new() -> Self {
         let pos_goals: Vec<Vector3<f64>> = Vec::new();
         let quat_goals: Vec<UnitQuaternion<f64>> = Vec::new();
-        Self{pos_goals, quat_goals}
+        let masks: Vec<GoalMask> = Vec::new();
+        let tolerances: Vec<GoalTolerance> = Vec::new();
+        let frames: Vec<String> = Vec::new();
+        Self{pos_goals, quat_goals, masks, tolerances, position_mode: None, rotation_mode: None, frames}
     }
 }
 /*
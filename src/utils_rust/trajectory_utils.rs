@@ -0,0 +1,263 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+use nalgebra::{Vector3, UnitQuaternion, Quaternion};
+use yaml_rust::{YamlLoader, Yaml};
+use crate::utils_rust::file_utils::get_file_contents;
+use crate::utils_rust::yaml_utils::{get_yaml_obj, yaml_f64, yaml_vec, yaml_vector3, parse_list_of_floats_1};
+use crate::utils_rust::subscriber_utils::EEPoseGoalsSubscriber;
+use crate::utils_rust::error_utils::RelaxedIKError;
+
+// one goal pose per chain, at time seconds from the start of the trajectory
+#[derive(Clone, Debug)]
+pub struct CartesianWaypoint {
+    pub time: f64,
+    pub pos_goals: Vec<Vector3<f64>>,
+    pub quat_goals: Vec<UnitQuaternion<f64>>
+}
+
+// a timed cartesian path for the end effectors, read from an animation file. the goals mean the same as the ones
+// passed to solve, so they are offsets from the starting pose unless the solver is in absolute mode.
+//
+// csv: one waypoint per line, time followed by x y z qx qy qz qw for each chain. a header line and lines
+// starting with # are skipped.
+// yaml: waypoints: [{time: 0.0, poses: [{position: [x, y, z], orientation: [qx, qy, qz, qw]}, ...]}, ...]
+#[derive(Clone, Debug)]
+pub struct CartesianTrajectory {
+    pub waypoints: Vec<CartesianWaypoint>
+}
+impl CartesianTrajectory {
+    pub fn new(waypoints: Vec<CartesianWaypoint>) -> Result<Self, RelaxedIKError> {
+        if waypoints.len() == 0 {
+            return Err(RelaxedIKError::InvalidArgument{message: "a trajectory needs at least one waypoint".to_string()});
+        }
+        let num_chains = waypoints[0].pos_goals.len();
+        for i in 0..waypoints.len() {
+            if waypoints[i].pos_goals.len() != num_chains || waypoints[i].quat_goals.len() != num_chains {
+                return Err(RelaxedIKError::InvalidArgument{message: format!("waypoint {} has {} positions and {} orientations, waypoint 0 has {} poses",
+                    i, waypoints[i].pos_goals.len(), waypoints[i].quat_goals.len(), num_chains)});
+            }
+            if !waypoints[i].time.is_finite() || (i > 0 && waypoints[i].time <= waypoints[i-1].time) {
+                return Err(RelaxedIKError::InvalidArgument{message: format!("waypoint times must increase, waypoint {} is at {}", i, waypoints[i].time)});
+            }
+        }
+        Ok(Self{waypoints})
+    }
+
+    // picks the format from the extension, .csv or .yaml / .yml
+    pub fn from_path(fp: String) -> Result<Self, RelaxedIKError> {
+        let extension = Path::new(fp.as_str()).extension().map(|e| e.to_string_lossy().to_lowercase());
+        match extension.as_ref().map(|e| e.as_str()) {
+            Some("csv") => CartesianTrajectory::from_csv_path(fp),
+            Some("yaml") | Some("yml") => CartesianTrajectory::from_yaml_path(fp),
+            _ => Err(RelaxedIKError::Parse{path: fp, message: "expected a .csv, .yaml or .yml animation file".to_string()})
+        }
+    }
+
+    pub fn from_csv_path(fp: String) -> Result<Self, RelaxedIKError> {
+        let contents = get_file_contents(fp.clone())?;
+        let mut waypoints: Vec<CartesianWaypoint> = Vec::new();
+        let mut first_line = true;
+        for (line_idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.len() == 0 || line.starts_with("#") {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|s| s.len() > 0).collect();
+            let values: Result<Vec<f64>, _> = fields.iter().map(|s| s.parse::<f64>()).collect();
+            let values = match values {
+                Ok(v) => v,
+                Err(_) if first_line => { first_line = false; continue; },
+                Err(e) => return Err(RelaxedIKError::Parse{path: fp, message: format!("line {}: {}", line_idx + 1, e)})
+            };
+            first_line = false;
+            if values.len() < 8 || (values.len() - 1) % 7 != 0 {
+                return Err(RelaxedIKError::Parse{path: fp, message: format!("line {}: expected a time and 7 values (x y z qx qy qz qw) per chain, got {} values",
+                    line_idx + 1, values.len())});
+            }
+            let (pos_goals, quat_goals) = poses_from_values(&values[1..]);
+            waypoints.push(CartesianWaypoint{time: values[0], pos_goals, quat_goals});
+        }
+        CartesianTrajectory::new(waypoints).map_err(|e| RelaxedIKError::Parse{path: fp, message: e.to_string()})
+    }
+
+    pub fn from_yaml_path(fp: String) -> Result<Self, RelaxedIKError> {
+        let yaml_files = get_yaml_obj(fp.clone())?;
+        let yaml_file = &yaml_files[0];
+        let f = fp.as_str();
+
+        let waypoints_list = yaml_vec(&yaml_file["waypoints"], f, "waypoints")?;
+        let mut waypoints: Vec<CartesianWaypoint> = Vec::new();
+        for i in 0..waypoints_list.len() {
+            let key = format!("waypoints[{}]", i);
+            let time = yaml_f64(&waypoints_list[i]["time"], f, format!("{}.time", key).as_str())?;
+            let (pos_goals, quat_goals) = yaml_poses(&waypoints_list[i]["poses"], f, format!("{}.poses", key).as_str())?;
+            waypoints.push(CartesianWaypoint{time, pos_goals, quat_goals});
+        }
+        CartesianTrajectory::new(waypoints).map_err(|e| RelaxedIKError::Parse{path: fp, message: e.to_string()})
+    }
+
+    pub fn num_chains(&self) -> usize {
+        self.waypoints[0].pos_goals.len()
+    }
+
+    pub fn start_time(&self) -> f64 {
+        self.waypoints[0].time
+    }
+
+    pub fn end_time(&self) -> f64 {
+        self.waypoints[self.waypoints.len() - 1].time
+    }
+
+    // the goals at time t, linear in position and slerped in orientation between waypoints, held outside them
+    pub fn sample(&self, t: f64) -> EEPoseGoalsSubscriber {
+        let mut out = EEPoseGoalsSubscriber::new();
+        let n = self.waypoints.len();
+        if t <= self.start_time() || n == 1 {
+            out.pos_goals = self.waypoints[0].pos_goals.clone();
+            out.quat_goals = self.waypoints[0].quat_goals.clone();
+            return out;
+        }
+        if t >= self.end_time() {
+            out.pos_goals = self.waypoints[n - 1].pos_goals.clone();
+            out.quat_goals = self.waypoints[n - 1].quat_goals.clone();
+            return out;
+        }
+
+        let mut i = 0;
+        while self.waypoints[i + 1].time < t {
+            i += 1;
+        }
+        let a = &self.waypoints[i];
+        let b = &self.waypoints[i + 1];
+        let s = (t - a.time) / (b.time - a.time);
+        for c in 0..a.pos_goals.len() {
+            out.pos_goals.push(a.pos_goals[c] + (b.pos_goals[c] - a.pos_goals[c]) * s);
+            // take the short way round, slerp is undefined for opposite quaternions
+            let mut qb = b.quat_goals[c];
+            if a.quat_goals[c].coords.dot(&qb.coords) < 0.0 {
+                qb = UnitQuaternion::new_unchecked(-qb.into_inner());
+            }
+            out.quat_goals.push(a.quat_goals[c].try_slerp(&qb, s, 1.0e-9).unwrap_or(a.quat_goals[c]));
+        }
+        out
+    }
+}
+
+// x y z qx qy qz qw for each chain, values.len() must be a multiple of 7
+fn poses_from_values(values: &[f64]) -> (Vec<Vector3<f64>>, Vec<UnitQuaternion<f64>>) {
+    let mut pos_goals: Vec<Vector3<f64>> = Vec::new();
+    let mut quat_goals: Vec<UnitQuaternion<f64>> = Vec::new();
+    for c in 0..values.len() / 7 {
+        let p = &values[7 * c..7 * c + 7];
+        pos_goals.push(Vector3::new(p[0], p[1], p[2]));
+        quat_goals.push(UnitQuaternion::from_quaternion(Quaternion::new(p[6], p[3], p[4], p[5])));
+    }
+    (pos_goals, quat_goals)
+}
+
+// [{position: [x, y, z], orientation: [qx, qy, qz, qw]}, ...], one per chain
+fn yaml_poses(y: &Yaml, fp: &str, key: &str) -> Result<(Vec<Vector3<f64>>, Vec<UnitQuaternion<f64>>), RelaxedIKError> {
+    let poses = yaml_vec(y, fp, key)?;
+    let mut pos_goals: Vec<Vector3<f64>> = Vec::new();
+    let mut quat_goals: Vec<UnitQuaternion<f64>> = Vec::new();
+    for c in 0..poses.len() {
+        let pose_key = format!("{}[{}]", key, c);
+        pos_goals.push(yaml_vector3(&poses[c]["position"], fp, format!("{}.position", pose_key).as_str())?);
+        let q_key = format!("{}.orientation", pose_key);
+        let q = parse_list_of_floats_1(&poses[c]["orientation"], fp, q_key.as_str())?;
+        if q.len() != 4 {
+            return Err(RelaxedIKError::InvalidValue{path: fp.to_string(), key: q_key, expected: format!("a list of 4 numbers (qx qy qz qw), got {}", q.len())});
+        }
+        quat_goals.push(UnitQuaternion::from_quaternion(Quaternion::new(q[3], q[0], q[1], q[2])));
+    }
+    Ok((pos_goals, quat_goals))
+}
+
+// untimed goals, one set of poses per line, for batch solving. picks the format from the extension:
+// csv: x y z qx qy qz qw for each chain, a header line and lines starting with # are skipped.
+// json lines (.jsonl, .json, .ndjson): {"poses": [{"position": [x, y, z], "orientation": [qx, qy, qz, qw]}, ...]}
+pub fn read_goal_list(fp: String) -> Result<Vec<EEPoseGoalsSubscriber>, RelaxedIKError> {
+    let extension = Path::new(fp.as_str()).extension().map(|e| e.to_string_lossy().to_lowercase());
+    let is_csv = match extension.as_ref().map(|e| e.as_str()) {
+        Some("csv") => true,
+        Some("jsonl") | Some("json") | Some("ndjson") => false,
+        _ => return Err(RelaxedIKError::Parse{path: fp, message: "expected a .csv or .jsonl goal file".to_string()})
+    };
+
+    let contents = get_file_contents(fp.clone())?;
+    let mut out: Vec<EEPoseGoalsSubscriber> = Vec::new();
+    let mut first_line = true;
+    for (line_idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.len() == 0 || line.starts_with("#") {
+            continue;
+        }
+        let mut goals = EEPoseGoalsSubscriber::new();
+        if is_csv {
+            let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|s| s.len() > 0).collect();
+            let values: Result<Vec<f64>, _> = fields.iter().map(|s| s.parse::<f64>()).collect();
+            let values = match values {
+                Ok(v) => v,
+                Err(_) if first_line => { first_line = false; continue; },
+                Err(e) => return Err(RelaxedIKError::Parse{path: fp, message: format!("line {}: {}", line_idx + 1, e)})
+            };
+            if values.len() == 0 || values.len() % 7 != 0 {
+                return Err(RelaxedIKError::Parse{path: fp, message: format!("line {}: expected 7 values (x y z qx qy qz qw) per chain, got {} values",
+                    line_idx + 1, values.len())});
+            }
+            let (pos_goals, quat_goals) = poses_from_values(&values);
+            goals.pos_goals = pos_goals;
+            goals.quat_goals = quat_goals;
+        } else {
+            let docs = YamlLoader::load_from_str(line).map_err(|e| RelaxedIKError::Parse{path: fp.clone(), message: format!("line {}: {}", line_idx + 1, e)})?;
+            if docs.len() == 0 {
+                continue;
+            }
+            let (pos_goals, quat_goals) = yaml_poses(&docs[0]["poses"], fp.as_str(), format!("line {}: poses", line_idx + 1).as_str())?;
+            goals.pos_goals = pos_goals;
+            goals.quat_goals = quat_goals;
+        }
+        first_line = false;
+        out.push(goals);
+    }
+    Ok(out)
+}
+
+// the solver output of a playback, one joint vector per sample
+#[derive(Clone, Debug)]
+pub struct JointTrajectory {
+    pub joint_names: Vec<String>,
+    pub times: Vec<f64>,
+    pub states: Vec<Vec<f64>>
+}
+impl JointTrajectory {
+    pub fn new(joint_names: Vec<String>) -> Self {
+        Self{joint_names, times: Vec::new(), states: Vec::new()}
+    }
+
+    pub fn push(&mut self, time: f64, state: Vec<f64>) {
+        self.times.push(time);
+        self.states.push(state);
+    }
+
+    // a header of time and the joint names, then one line per sample
+    pub fn write_csv(&self, fp: String) -> Result<(), RelaxedIKError> {
+        let mut contents = String::from("time");
+        for i in 0..self.joint_names.len() {
+            contents += format!(",{}", self.joint_names[i]).as_str();
+        }
+        contents += "\n";
+        for i in 0..self.times.len() {
+            contents += format!("{}", self.times[i]).as_str();
+            for j in 0..self.states[i].len() {
+                contents += format!(",{}", self.states[i][j]).as_str();
+            }
+            contents += "\n";
+        }
+
+        let mut file = File::create(fp.as_str()).map_err(|e| RelaxedIKError::Io{path: fp.clone(), source: e})?;
+        file.write_all(contents.as_bytes()).map_err(|e| RelaxedIKError::Io{path: fp.clone(), source: e})?;
+        Ok(())
+    }
+}
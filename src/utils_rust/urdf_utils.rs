@@ -0,0 +1,379 @@
+use crate::utils_rust::file_utils::{*};
+use crate::utils_rust::error_utils::RelaxedIKError;
+use nalgebra::{Vector3, UnitQuaternion};
+
+#[derive(Clone, Debug)]
+pub struct UrdfJoint {
+    pub name: String,
+    pub joint_type: String,
+    pub parent: String,
+    pub child: String,
+    pub origin_xyz: Vector3<f64>,
+    pub origin_rpy: Vector3<f64>,
+    pub axis: Vector3<f64>,
+    pub lower: f64,
+    pub upper: f64,
+    pub velocity: f64
+}
+impl UrdfJoint {
+    pub fn new(name: String, joint_type: String) -> Self {
+        Self{name, joint_type, parent: String::new(), child: String::new(), origin_xyz: Vector3::zeros(), origin_rpy: Vector3::zeros(),
+            axis: Vector3::new(1.0, 0.0, 0.0), lower: -2.0 * std::f64::consts::PI, upper: 2.0 * std::f64::consts::PI, velocity: 1.0}
+    }
+
+    pub fn is_movable(&self) -> bool {
+        self.joint_type == "revolute" || self.joint_type == "continuous" || self.joint_type == "prismatic"
+    }
+
+    // None if the axis isn't aligned with x, y or z
+    pub fn get_axis_type(&self) -> Option<String> {
+        let a = self.axis;
+        if a[1].abs() < 1e-6 && a[2].abs() < 1e-6 {
+            if a[0] > 0.0 { return Some("x".to_string()) } else { return Some("-x".to_string()) }
+        } else if a[0].abs() < 1e-6 && a[2].abs() < 1e-6 {
+            if a[1] > 0.0 { return Some("y".to_string()) } else { return Some("-y".to_string()) }
+        } else if a[0].abs() < 1e-6 && a[1].abs() < 1e-6 {
+            if a[2] > 0.0 { return Some("z".to_string()) } else { return Some("-z".to_string()) }
+        }
+        None
+    }
+}
+
+// everything an Arm (plus the matching Robot fields) needs for one chain
+#[derive(Clone, Debug)]
+pub struct UrdfChain {
+    // one per displacement, fixed joints included, like the joint_names of an info file
+    pub joint_names: Vec<String>,
+    // one per movable joint
+    pub axis_types: Vec<String>,
+    pub displacements: Vec<Vector3<f64>>,
+    pub disp_offset: Vector3<f64>,
+    pub rot_offsets: Vec<Vec<f64>>,
+    pub joint_types: Vec<String>,
+    pub joint_limits: Vec<[f64; 2]>,
+    pub velocity_limits: Vec<f64>
+}
+
+pub struct UrdfParser {
+    pub file_path: String,
+    pub robot_name: String,
+    pub links: Vec<String>,
+    pub joints: Vec<UrdfJoint>
+}
+impl UrdfParser {
+    pub fn from_urdf_path(fp: String) -> Result<Self, RelaxedIKError> {
+        let contents = get_file_contents(fp.clone())?;
+        UrdfParser::parse(contents.as_str(), fp)
+    }
+
+    pub fn from_urdf_string(s: &str) -> Result<Self, RelaxedIKError> {
+        UrdfParser::parse(s, "<urdf string>".to_string())
+    }
+
+    fn parse(s: &str, file_path: String) -> Result<Self, RelaxedIKError> {
+        let tags = scan_xml_tags(s);
+        let err = |message: String| RelaxedIKError::Urdf{path: file_path.clone(), message};
+
+        let mut robot_name = String::new();
+        let mut links: Vec<String> = Vec::new();
+        let mut joints: Vec<UrdfJoint> = Vec::new();
+
+        // only direct children of <robot> are links and joints, <joint> also shows up inside <transmission>
+        let mut depth: i32 = 0;
+        let mut curr_joint: Option<UrdfJoint> = None;
+        for tag in &tags {
+            if tag.closing {
+                depth -= 1;
+                if depth == 1 && tag.name == "joint" {
+                    if let Some(j) = curr_joint.take() {
+                        joints.push(j);
+                    }
+                }
+                continue;
+            }
+
+            if depth == 0 && tag.name == "robot" {
+                robot_name = tag.get_attr("name").unwrap_or("").to_string();
+            } else if depth == 1 && tag.name == "link" {
+                links.push(tag.get_attr("name").ok_or_else(|| err(format!("link {} has no name", links.len())))?.to_string());
+            } else if depth == 1 && tag.name == "joint" {
+                let name = tag.get_attr("name").ok_or_else(|| err(format!("joint {} has no name", joints.len())))?.to_string();
+                let joint_type = tag.get_attr("type").ok_or_else(|| err(format!("joint {} has no type", name)))?.to_string();
+                let j = UrdfJoint::new(name, joint_type);
+                if tag.self_closing {
+                    joints.push(j);
+                } else {
+                    curr_joint = Some(j);
+                }
+            } else if depth == 2 {
+                if let Some(j) = curr_joint.as_mut() {
+                    let jname = j.name.clone();
+                    if tag.name == "parent" {
+                        j.parent = tag.get_attr("link").unwrap_or("").to_string();
+                    } else if tag.name == "child" {
+                        j.child = tag.get_attr("link").unwrap_or("").to_string();
+                    } else if tag.name == "origin" {
+                        let bad = |attr: &str| err(format!("joint {} has a malformed origin {}", jname, attr));
+                        if let Some(xyz) = tag.get_attr("xyz") { j.origin_xyz = parse_vector3(xyz).ok_or_else(|| bad("xyz"))?; }
+                        if let Some(rpy) = tag.get_attr("rpy") { j.origin_rpy = parse_vector3(rpy).ok_or_else(|| bad("rpy"))?; }
+                    } else if tag.name == "axis" {
+                        if let Some(xyz) = tag.get_attr("xyz") { j.axis = parse_vector3(xyz).ok_or_else(|| err(format!("joint {} has a malformed axis", jname)))?; }
+                    } else if tag.name == "limit" {
+                        let bad = |attr: &str| err(format!("joint {} has a malformed limit {}", jname, attr));
+                        if let Some(l) = tag.get_attr("lower") { j.lower = l.trim().parse::<f64>().map_err(|_| bad("lower"))?; }
+                        if let Some(u) = tag.get_attr("upper") { j.upper = u.trim().parse::<f64>().map_err(|_| bad("upper"))?; }
+                        if let Some(v) = tag.get_attr("velocity") { j.velocity = v.trim().parse::<f64>().map_err(|_| bad("velocity"))?; }
+                    }
+                }
+            }
+
+            if !tag.self_closing {
+                depth += 1;
+            }
+        }
+
+        Ok(Self{file_path, robot_name, links, joints})
+    }
+
+    fn error(&self, message: String) -> RelaxedIKError {
+        RelaxedIKError::Urdf{path: self.file_path.clone(), message}
+    }
+
+    pub fn get_joint_by_child(&self, child_link: &str) -> Option<&UrdfJoint> {
+        self.joints.iter().find(|j| j.child == child_link)
+    }
+
+    pub fn get_joint_by_name(&self, joint_name: &str) -> Option<&UrdfJoint> {
+        self.joints.iter().find(|j| j.name == joint_name)
+    }
+
+    // all joints from base_link down to ee_link, in order
+    pub fn get_joint_chain(&self, base_link: &str, ee_link: &str) -> Result<Vec<UrdfJoint>, RelaxedIKError> {
+        let mut out: Vec<UrdfJoint> = Vec::new();
+        let mut curr_link = ee_link.to_string();
+        while curr_link != base_link {
+            let j = match self.get_joint_by_child(curr_link.as_str()) {
+                Some(j) => j,
+                None => return Err(self.error(format!("link {} is not connected to base link {}", curr_link, base_link)))
+            };
+            out.push(j.clone());
+            curr_link = j.parent.clone();
+        }
+        out.reverse();
+        Ok(out)
+    }
+
+    pub fn get_chain(&self, base_link: &str, ee_link: &str) -> Result<UrdfChain, RelaxedIKError> {
+        let joints = self.get_joint_chain(base_link, ee_link)?;
+        self.get_chain_from_joints(&joints, base_link, ee_link)
+    }
+
+    // same as get_chain, but ends on the ee fixed joint of an info file, which is treated as fixed whatever its urdf type
+    pub fn get_chain_to_ee_fixed_joint(&self, base_link: &str, ee_fixed_joint: &str) -> Result<UrdfChain, RelaxedIKError> {
+        let ee_link = match self.get_joint_by_name(ee_fixed_joint) {
+            Some(j) => j.child.clone(),
+            None => return Err(self.error(format!("ee fixed joint {} is not in the urdf", ee_fixed_joint)))
+        };
+        let mut joints = self.get_joint_chain(base_link, ee_link.as_str())?;
+        let last_idx = joints.len() - 1;
+        joints[last_idx].joint_type = "fixed".to_string();
+        self.get_chain_from_joints(&joints, base_link, ee_link.as_str())
+    }
+
+    fn get_chain_from_joints(&self, joints: &Vec<UrdfJoint>, base_link: &str, ee_link: &str) -> Result<UrdfChain, RelaxedIKError> {
+        // fixed joints ahead of the first movable joint are folded into the disp offset and first rot offset
+        let first_movable = match joints.iter().position(|j| j.is_movable()) {
+            Some(i) => i,
+            None => return Err(self.error(format!("no movable joints between {} and {}", base_link, ee_link)))
+        };
+        let mut pt: Vector3<f64> = Vector3::zeros();
+        let mut rot: UnitQuaternion<f64> = UnitQuaternion::identity();
+        for i in 0..first_movable + 1 {
+            pt = rot * joints[i].origin_xyz + pt;
+            rot = rot * get_rpy_quat(&joints[i].origin_rpy);
+        }
+        let (r, p, y) = rot.euler_angles();
+
+        let mut joint_names: Vec<String> = Vec::new();
+        let mut axis_types: Vec<String> = Vec::new();
+        let mut displacements: Vec<Vector3<f64>> = Vec::new();
+        let mut rot_offsets: Vec<Vec<f64>> = vec![vec![r, p, y]];
+        let mut joint_types: Vec<String> = Vec::new();
+        let mut joint_limits: Vec<[f64; 2]> = Vec::new();
+        let mut velocity_limits: Vec<f64> = Vec::new();
+
+        for i in first_movable..joints.len() {
+            let j = &joints[i];
+            if !j.is_movable() && j.joint_type != "fixed" {
+                return Err(self.error(format!("joint {} has unsupported type {}", j.name, j.joint_type)));
+            }
+            joint_names.push(j.name.clone());
+            joint_types.push(j.joint_type.clone());
+            if j.is_movable() {
+                let axis_type = match j.get_axis_type() {
+                    Some(a) => a,
+                    None => return Err(self.error(format!("joint {} has axis {:?}, only axes aligned with x, y or z are supported", j.name, j.axis)))
+                };
+                axis_types.push(axis_type);
+                joint_limits.push([j.lower, j.upper]);
+                velocity_limits.push(j.velocity);
+            }
+
+            if i + 1 < joints.len() {
+                displacements.push(joints[i + 1].origin_xyz.clone());
+                let rpy = &joints[i + 1].origin_rpy;
+                rot_offsets.push(vec![rpy[0], rpy[1], rpy[2]]);
+            } else {
+                // the chain ends on this joint, so the end effector sits right at its frame
+                displacements.push(Vector3::zeros());
+                rot_offsets.push(vec![0.0, 0.0, 0.0]);
+            }
+        }
+
+        // a trailing fixed joint (e.g. ee_fixed_joint) is already covered by the displacement into it
+        if !joints[joints.len() - 1].is_movable() {
+            joint_names.pop();
+            joint_types.pop();
+            displacements.pop();
+            rot_offsets.pop();
+        }
+
+        Ok(UrdfChain{joint_names, axis_types, displacements, disp_offset: pt, rot_offsets, joint_types, joint_limits, velocity_limits})
+    }
+}
+
+pub fn get_rpy_quat(rpy: &Vector3<f64>) -> UnitQuaternion<f64> {
+    UnitQuaternion::from_euler_angles(rpy[0], rpy[1], rpy[2])
+}
+
+pub fn parse_vector3(s: &str) -> Option<Vector3<f64>> {
+    let v: Vec<f64> = s.split_whitespace().map(|x| x.parse::<f64>().ok()).collect::<Option<Vec<f64>>>()?;
+    if v.len() != 3 {
+        return None;
+    }
+    Some(Vector3::new(v[0], v[1], v[2]))
+}
+
+#[derive(Clone, Debug)]
+pub struct XmlTag {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub closing: bool,
+    pub self_closing: bool
+}
+impl XmlTag {
+    pub fn get_attr(&self, key: &str) -> Option<&str> {
+        for (k, v) in &self.attrs {
+            if k == key {
+                return Some(v.as_str());
+            }
+        }
+        None
+    }
+}
+
+// a minimal tag scanner, good enough for the element/attribute subset of xml used by urdf files
+pub fn scan_xml_tags(s: &str) -> Vec<XmlTag> {
+    let mut out: Vec<XmlTag> = Vec::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        if rest.starts_with("<!--") {
+            match rest.find("-->") {
+                Some(end) => { rest = &rest[end + 3..]; continue; }
+                None => break
+            }
+        }
+        if rest.starts_with("<?") || rest.starts_with("<!") {
+            match rest.find('>') {
+                Some(end) => { rest = &rest[end + 1..]; continue; }
+                None => break
+            }
+        }
+
+        let end = match rest.find('>') {
+            Some(e) => e,
+            None => break
+        };
+        let mut inner = rest[1..end].trim();
+        rest = &rest[end + 1..];
+
+        let closing = inner.starts_with('/');
+        if closing {
+            inner = inner[1..].trim();
+        }
+        let self_closing = inner.ends_with('/');
+        if self_closing {
+            inner = inner[..inner.len() - 1].trim();
+        }
+
+        let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+        let name = inner[..name_end].to_string();
+        let attrs = parse_xml_attrs(&inner[name_end..]);
+        out.push(XmlTag{name, attrs, closing, self_closing});
+    }
+    out
+}
+
+fn parse_xml_attrs(s: &str) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim().to_string();
+        let after = rest[eq + 1..].trim_start();
+        let quote = match after.chars().next() {
+            Some(c) if c == '"' || c == '\'' => c,
+            _ => break
+        };
+        let value_end = match after[1..].find(quote) {
+            Some(e) => e + 1,
+            None => break
+        };
+        out.push((key, after[1..value_end].to_string()));
+        rest = after[value_end + 1..].trim_start();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spacetime::robot::Robot;
+
+    fn config_path(sub_path: &str) -> String {
+        format!("{}/config/{}", env!("CARGO_MANIFEST_DIR"), sub_path)
+    }
+
+    // the end effector poses of the urdf-built robot at a few configs against the info file's
+    fn check_fk_matches_info_file(info_file: &str, urdf_file: &str, base_link: &str, ee_link: &str) {
+        let info_robot = Robot::from_yaml_path(config_path(format!("info_files/{}", info_file).as_str())).unwrap();
+        let urdf_robot = Robot::from_urdf_path(config_path(format!("urdfs/{}", urdf_file).as_str()), &vec![base_link.to_string()], &vec![ee_link.to_string()]).unwrap();
+        assert_eq!(urdf_robot.joint_ordering, info_robot.joint_ordering);
+        assert_eq!(urdf_robot.joint_names[0].len(), urdf_robot.arms[0].displacements.len());
+
+        for k in 0..4 {
+            let x: Vec<f64> = (0..info_robot.num_dof).map(|i| 0.3 * (k as f64) - 0.2 * (i as f64) + 0.1).collect();
+            let info_ee = info_robot.get_ee_pos_and_quat_immutable(&x);
+            let urdf_ee = urdf_robot.get_ee_pos_and_quat_immutable(&x);
+            assert!((urdf_ee[0].0 - info_ee[0].0).norm() < 1e-6, "{} position at config {}", info_file, k);
+            assert!(urdf_ee[0].1.angle_to(&info_ee[0].1) < 1e-6, "{} rotation at config {}", info_file, k);
+        }
+    }
+
+    #[test]
+    fn urdf_fk_matches_panda_info_file() {
+        check_fk_matches_info_file("panda_info.yaml", "panda_arm_hand.urdf", "panda_link0", "panda_hand");
+        let urdf_robot = Robot::from_urdf_path(config_path("urdfs/panda_arm_hand.urdf"), &vec!["panda_link0".to_string()], &vec!["panda_hand".to_string()]).unwrap();
+        let info_robot = Robot::from_yaml_path(config_path("info_files/panda_info.yaml")).unwrap();
+        assert_eq!(urdf_robot.joint_names, info_robot.joint_names);
+    }
+
+    #[test]
+    fn urdf_fk_matches_ur5_info_file() {
+        check_fk_matches_info_file("ur5_info.yaml", "ur5.urdf", "base_link", "ee_link");
+    }
+
+    #[test]
+    fn urdf_fk_matches_iiwa7_info_file() {
+        check_fk_matches_info_file("iiwa7_info.yaml", "iiwa7.urdf", "world", "iiwa_link_ee");
+    }
+}
@@ -5,16 +5,81 @@ use nalgebra;
 use nalgebra::{DMatrix, DVector};
 use crate::utils_rust::file_utils::{*};
 use crate::utils_rust::shape_parser_utils::{*};
-use std::path::Path;
+use crate::utils_rust::urdf_utils::UrdfParser;
+use crate::utils_rust::error_utils::RelaxedIKError;
+use crate::groove::objective::GrooveLossParams;
+use crate::utils_rust::trajectory_utils::CartesianTrajectory;
+use crate::utils_rust::subscriber_utils::GoalFrame;
 use std::io;
 
-pub fn get_yaml_obj(fp: String) -> Vec<Yaml> {
-    let mut file = File::open(fp.as_str()).unwrap();
-    let mut contents = String::new();
-    let res = file.read_to_string(&mut contents).unwrap();
+pub fn get_yaml_obj(fp: String) -> Result<Vec<Yaml>, RelaxedIKError> {
+    let contents = get_file_contents(fp.clone())?;
 
-    let docs = YamlLoader::load_from_str(contents.as_str()).unwrap();
-    docs
+    let docs = YamlLoader::load_from_str(contents.as_str()).map_err(|e| RelaxedIKError::Parse{path: fp.clone(), message: e.to_string()})?;
+    if docs.len() == 0 {
+        return Err(RelaxedIKError::Parse{path: fp, message: "file is empty".to_string()});
+    }
+    Ok(docs)
+}
+
+// typed accessors, key is the full path of y inside the file (e.g. "displacements[0][2]") so errors can point at it
+fn yaml_error(y: &Yaml, fp: &str, key: &str, expected: &str) -> RelaxedIKError {
+    if y.is_badvalue() {
+        RelaxedIKError::MissingKey{path: fp.to_string(), key: key.to_string()}
+    } else {
+        RelaxedIKError::InvalidValue{path: fp.to_string(), key: key.to_string(), expected: expected.to_string()}
+    }
+}
+
+pub fn yaml_str(y: &Yaml, fp: &str, key: &str) -> Result<String, RelaxedIKError> {
+    match y.as_str() {
+        Some(s) => Ok(s.to_string()),
+        None => Err(yaml_error(y, fp, key, "a string"))
+    }
+}
+
+pub fn yaml_f64(y: &Yaml, fp: &str, key: &str) -> Result<f64, RelaxedIKError> {
+    match y {
+        Yaml::Real(_) => Ok(y.as_f64().unwrap()),
+        Yaml::Integer(i) => Ok(*i as f64),
+        _ => Err(yaml_error(y, fp, key, "a number"))
+    }
+}
+
+pub fn yaml_usize(y: &Yaml, fp: &str, key: &str) -> Result<usize, RelaxedIKError> {
+    match y {
+        Yaml::Integer(i) if *i >= 0 => Ok(*i as usize),
+        _ => Err(yaml_error(y, fp, key, "a non-negative integer"))
+    }
+}
+
+pub fn yaml_bool(y: &Yaml, fp: &str, key: &str) -> Result<bool, RelaxedIKError> {
+    match y.as_bool() {
+        Some(b) => Ok(b),
+        None => Err(yaml_error(y, fp, key, "true or false"))
+    }
+}
+
+pub fn yaml_vec<'a>(y: &'a Yaml, fp: &str, key: &str) -> Result<&'a Vec<Yaml>, RelaxedIKError> {
+    match y.as_vec() {
+        Some(v) => Ok(v),
+        None => Err(yaml_error(y, fp, key, "a list"))
+    }
+}
+
+pub fn yaml_vector3(y: &Yaml, fp: &str, key: &str) -> Result<nalgebra::Vector3<f64>, RelaxedIKError> {
+    let v = parse_list_of_floats_1(y, fp, key)?;
+    if v.len() != 3 {
+        return Err(RelaxedIKError::InvalidValue{path: fp.to_string(), key: key.to_string(), expected: format!("a list of 3 numbers, got {}", v.len())});
+    }
+    Ok(nalgebra::Vector3::new(v[0], v[1], v[2]))
+}
+
+fn check_len(len: usize, expected: usize, fp: &str, key: &str, what: &str) -> Result<(), RelaxedIKError> {
+    if len != expected {
+        return Err(RelaxedIKError::InvalidValue{path: fp.to_string(), key: key.to_string(), expected: format!("{} entries (one per {}), got {}", expected, what, len)});
+    }
+    Ok(())
 }
 
 
@@ -35,22 +100,30 @@ pub struct InfoFileParser {
     pub disp_offsets: Vec<nalgebra::Vector3<f64>>,
     pub rot_offsets: Vec<Vec<Vec<f64>>>,
     pub joint_types: Vec<Vec<String>>,
-    pub joint_state_define_func_file: String
+    pub joint_state_define_func_file: String,
+    // optional, one per chain, identity without tcp_offsets
+    pub tcp_offsets: Vec<nalgebra::Isometry3<f64>>
 }
 impl InfoFileParser {
-    pub fn from_yaml_path(fp: String) -> InfoFileParser {
-        let docs = get_yaml_obj(fp);
-        let doc = &docs[0];
+    pub fn from_yaml_path(fp: String) -> Result<InfoFileParser, RelaxedIKError> {
+        InfoFileParser::from_yaml_path_with_paths(fp, &ConfigPaths::from_env())
+    }
 
-        let urdf_file_name = String::from(doc["urdf_file_name"].as_str().unwrap());
-        let fixed_frame = String::from(doc["fixed_frame"].as_str().unwrap());
-        let mut joint_names: Vec<Vec<String>> = Vec::new();
-        let mut joint_ordering: Vec<String> = Vec::new();
-        let mut ee_fixed_joints: Vec<String> = Vec::new();
-        let mut starting_config: Vec<f64> = Vec::new();
-        let collision_file_name = String::from(doc["collision_file_name"].as_str().unwrap());
-        let collision_nn_file = String::from(doc["collision_nn_file"].as_str().unwrap());
-        let path_to_src = String::from(doc["path_to_src"].as_str().unwrap());
+    // paths is only used to find the urdf, when the info file has no pre-generated kinematics
+    pub fn from_yaml_path_with_paths(fp: String, paths: &ConfigPaths) -> Result<InfoFileParser, RelaxedIKError> {
+        let docs = get_yaml_obj(fp.clone())?;
+        let doc = &docs[0];
+        let f = fp.as_str();
+
+        let urdf_file_name = yaml_str(&doc["urdf_file_name"], f, "urdf_file_name")?;
+        let fixed_frame = yaml_str(&doc["fixed_frame"], f, "fixed_frame")?;
+        let joint_names = parse_list_of_strings_2(&doc["joint_names"], f, "joint_names")?;
+        let joint_ordering = parse_list_of_strings_1(&doc["joint_ordering"], f, "joint_ordering")?;
+        let ee_fixed_joints = parse_list_of_strings_1(&doc["ee_fixed_joints"], f, "ee_fixed_joints")?;
+        let starting_config = parse_list_of_floats_1(&doc["starting_config"], f, "starting_config")?;
+        let collision_file_name = yaml_str(&doc["collision_file_name"], f, "collision_file_name")?;
+        let collision_nn_file = yaml_str(&doc["collision_nn_file"], f, "collision_nn_file")?;
+        let path_to_src = yaml_str(&doc["path_to_src"], f, "path_to_src")?;
         let mut axis_types: Vec<Vec<String>> = Vec::new();
         let mut velocity_limits: Vec<f64> = Vec::new();
         let mut joint_limits: Vec<[ f64; 2] > = Vec::new();
@@ -58,90 +131,100 @@ impl InfoFileParser {
         let mut disp_offsets: Vec<nalgebra::Vector3<f64>> = Vec::new();
         let mut rot_offsets: Vec<Vec<Vec<f64>>> = Vec::new();
         let mut joint_types: Vec<Vec<String>> = Vec::new();
-        let joint_state_define_func_file = String::from(doc["joint_state_define_func_file"].as_str().unwrap() );
-
-        let joint_names_arr = doc["joint_names"].as_vec().unwrap();
-        for i in 0..joint_names_arr.len() {
-            let mut joint_names2: Vec<String> = Vec::new();
-            joint_names.push(joint_names2);
-            let joint_names_arr2 = joint_names_arr[i].as_vec().unwrap();
-            for j in 0..joint_names_arr2.len() {
-                joint_names[i].push( String::from(joint_names_arr2[j].as_str().unwrap()) );
+        let joint_state_define_func_file = yaml_str(&doc["joint_state_define_func_file"], f, "joint_state_define_func_file")?;
+
+        if doc["axis_types"].is_badvalue() {
+            // no pre-generated kinematics in the info file, derive them from the urdf instead
+            let urdf_path = paths.urdf(urdf_file_name.as_str());
+            let urdf = UrdfParser::from_urdf_path(urdf_path)?;
+            for i in 0..ee_fixed_joints.len() {
+                let chain = urdf.get_chain_to_ee_fixed_joint(fixed_frame.as_str(), ee_fixed_joints[i].as_str())?;
+                axis_types.push(chain.axis_types);
+                displacements.push(chain.displacements);
+                disp_offsets.push(chain.disp_offset);
+                rot_offsets.push(chain.rot_offsets);
+                joint_types.push(chain.joint_types);
             }
-        }
 
-        let joint_ordering_arr = doc["joint_ordering"].as_vec().unwrap();
-        for i in 0..joint_ordering_arr.len() {
-            joint_ordering.push( String::from(joint_ordering_arr[i].as_str().unwrap()) );
-        }
-
-        let ee_fixed_joints_arr = doc["ee_fixed_joints"].as_vec().unwrap();
-        for i in 0..ee_fixed_joints_arr.len() {
-            ee_fixed_joints.push( String::from(ee_fixed_joints_arr[i].as_str().unwrap()) );
-        }
-
-        let starting_config_arr = doc["starting_config"].as_vec().unwrap();
-        for i in 0..starting_config_arr.len() {
-            starting_config.push(starting_config_arr[i].as_f64().unwrap());
-        }
-
-        let axis_types_arr = doc["axis_types"].as_vec().unwrap();
-        for i in 0..axis_types_arr.len() {
-            let mut str_vec: Vec<String> = Vec::new();
-            axis_types.push(str_vec);
-            let axis_types_arr2 = axis_types_arr[i].as_vec().unwrap();
-            for j in 0..axis_types_arr2.len() {
-                axis_types[i].push(String::from(axis_types_arr2[j].as_str().unwrap() ) );
+            for i in 0..joint_ordering.len() {
+                let joint = match urdf.get_joint_by_name(joint_ordering[i].as_str()) {
+                    Some(j) => j,
+                    None => return Err(RelaxedIKError::Urdf{path: urdf.file_path.clone(), message: format!("joint {} (joint_ordering[{}]) is not in the urdf", joint_ordering[i], i)})
+                };
+                velocity_limits.push(joint.velocity);
+                joint_limits.push([joint.lower, joint.upper]);
+            }
+        } else {
+            axis_types = parse_list_of_strings_2(&doc["axis_types"], f, "axis_types")?;
+            velocity_limits = parse_list_of_floats_1(&doc["velocity_limits"], f, "velocity_limits")?;
+
+            let joint_limits_arr = yaml_vec(&doc["joint_limits"], f, "joint_limits")?;
+            for i in 0..joint_limits_arr.len() {
+                let l = parse_list_of_floats_1(&joint_limits_arr[i], f, format!("joint_limits[{}]", i).as_str())?;
+                check_len(l.len(), 2, f, format!("joint_limits[{}]", i).as_str(), "bound")?;
+                joint_limits.push( [ l[0], l[1] ] )
             }
-        }
 
-        let velocity_limits_arr = doc["velocity_limits"].as_vec().unwrap();
-        for i in 0..velocity_limits_arr.len() {
-            velocity_limits.push(velocity_limits_arr[i].as_f64().unwrap());
-        }
+            let displacememts_arr = yaml_vec(&doc["displacements"], f, "displacements")?;
+            for i in 0..displacememts_arr.len() {
+                let vec3_vec: Vec<nalgebra::Vector3<f64>> = Vec::new();
+                displacements.push(vec3_vec);
+                let displacememts_arr2 = yaml_vec(&displacememts_arr[i], f, format!("displacements[{}]", i).as_str())?;
+                for j in 0..displacememts_arr2.len() {
+                    displacements[i].push( yaml_vector3(&displacememts_arr2[j], f, format!("displacements[{}][{}]", i, j).as_str())? )
+                }
+            }
 
-        let joint_limits_arr = doc["joint_limits"].as_vec().unwrap();
-        for i in 0..joint_limits_arr.len() {
-            joint_limits.push( [ joint_limits_arr[i][0].as_f64().unwrap(), joint_limits_arr[i][1].as_f64().unwrap() ] )
-        }
+            let disp_offsets_arr = yaml_vec(&doc["disp_offsets"], f, "disp_offsets")?;
+            for i in 0..disp_offsets_arr.len() {
+                disp_offsets.push( yaml_vector3(&disp_offsets_arr[i], f, format!("disp_offsets[{}]", i).as_str())? )
+            }
 
-        let displacememts_arr = doc["displacements"].as_vec().unwrap();
-        for i in 0..displacememts_arr.len() {
-            let vec3_vec: Vec<nalgebra::Vector3<f64>> = Vec::new();
-            displacements.push(vec3_vec);
-            let displacememts_arr2 = displacememts_arr[i].as_vec().unwrap();
-            for j in 0..displacememts_arr2.len() {
-                displacements[i].push( nalgebra::Vector3::new( displacememts_arr2[j][0].as_f64().unwrap(), displacememts_arr2[j][1].as_f64().unwrap(), displacememts_arr2[j][2].as_f64().unwrap() ) )
+            let rot_offsets_arr = yaml_vec(&doc["rot_offsets"], f, "rot_offsets")?;
+            for i in 0..rot_offsets_arr.len() {
+                let r: Vec<Vec<f64>> = Vec::new();
+                rot_offsets.push(r);
+                let rot_offsets_arr2 = yaml_vec(&rot_offsets_arr[i], f, format!("rot_offsets[{}]", i).as_str())?;
+                for j in 0..rot_offsets_arr2.len() {
+                    let v = yaml_vector3(&rot_offsets_arr2[j], f, format!("rot_offsets[{}][{}]", i, j).as_str())?;
+                    rot_offsets[i].push( vec![ v[0], v[1], v[2] ] )
+                }
             }
-        }
 
-        let disp_offsets_arr = doc["disp_offsets"].as_vec().unwrap();
-        for i in 0..disp_offsets_arr.len() {
-            disp_offsets.push( nalgebra::Vector3::new( disp_offsets_arr[i][0].as_f64().unwrap(), disp_offsets_arr[i][1].as_f64().unwrap(), disp_offsets_arr[i][2].as_f64().unwrap()  ) )
+            joint_types = parse_list_of_strings_2(&doc["joint_types"], f, "joint_types")?;
         }
 
-        let rot_offsets_arr = doc["rot_offsets"].as_vec().unwrap();
-        for i in 0..rot_offsets_arr.len() {
-            let r: Vec<Vec<f64>> = Vec::new();
-            rot_offsets.push(r);
-            let rot_offsets_arr2 = rot_offsets_arr[i].as_vec().unwrap();
-            for j in 0..rot_offsets_arr2.len() {
-                rot_offsets[i].push( vec![ rot_offsets_arr2[j][0].as_f64().unwrap(), rot_offsets_arr2[j][1].as_f64().unwrap(), rot_offsets_arr2[j][2].as_f64().unwrap() ] )
+        // every per-chain list has to line up with the chains, every per-joint list with joint_ordering
+        let num_chains = joint_names.len();
+        let mut tcp_offsets: Vec<nalgebra::Isometry3<f64>> = Vec::new();
+        if doc["tcp_offsets"].is_badvalue() {
+            tcp_offsets = vec![nalgebra::Isometry3::identity(); num_chains];
+        } else {
+            let tcp_offsets_arr = yaml_vec(&doc["tcp_offsets"], f, "tcp_offsets")?;
+            for i in 0..tcp_offsets_arr.len() {
+                tcp_offsets.push( yaml_isometry(&tcp_offsets_arr[i], f, format!("tcp_offsets[{}]", i).as_str())? );
             }
         }
-
-        let joint_types_arr = doc["joint_types"].as_vec().unwrap();
-        for i in 0..joint_types_arr.len() {
-            let str_vec: Vec<String> = Vec::new();
-            joint_types.push(str_vec);
-            let joint_types_arr2 = joint_types_arr[i].as_vec().unwrap();
-            for j in 0..joint_types_arr2.len() {
-                joint_types[i].push(String::from(joint_types_arr2[j].as_str().unwrap() ) );
-            }
+        check_len(ee_fixed_joints.len(), num_chains, f, "ee_fixed_joints", "chain in joint_names")?;
+        check_len(axis_types.len(), num_chains, f, "axis_types", "chain in joint_names")?;
+        check_len(displacements.len(), num_chains, f, "displacements", "chain in joint_names")?;
+        check_len(disp_offsets.len(), num_chains, f, "disp_offsets", "chain in joint_names")?;
+        check_len(rot_offsets.len(), num_chains, f, "rot_offsets", "chain in joint_names")?;
+        check_len(joint_types.len(), num_chains, f, "joint_types", "chain in joint_names")?;
+        check_len(tcp_offsets.len(), num_chains, f, "tcp_offsets", "chain in joint_names")?;
+        for i in 0..num_chains {
+            check_len(rot_offsets[i].len(), displacements[i].len() + 1, f, format!("rot_offsets[{}]", i).as_str(), "displacement, plus one,")?;
+            check_len(joint_types[i].len(), displacements[i].len(), f, format!("joint_types[{}]", i).as_str(), "displacement")?;
+            check_len(joint_names[i].len(), displacements[i].len(), f, format!("joint_names[{}]", i).as_str(), "displacement")?;
+            let num_movable = joint_types[i].iter().filter(|t| t.as_str() != "fixed").count();
+            check_len(axis_types[i].len(), num_movable, f, format!("axis_types[{}]", i).as_str(), "non-fixed joint in joint_types")?;
         }
+        check_len(velocity_limits.len(), joint_ordering.len(), f, "velocity_limits", "joint in joint_ordering")?;
+        check_len(joint_limits.len(), joint_ordering.len(), f, "joint_limits", "joint in joint_ordering")?;
+        check_len(starting_config.len(), joint_ordering.len(), f, "starting_config", "joint in joint_ordering")?;
 
-        InfoFileParser{urdf_file_name, fixed_frame, joint_names, joint_ordering, ee_fixed_joints, starting_config, collision_file_name, collision_nn_file, path_to_src, axis_types, velocity_limits,
-            joint_limits, displacements, disp_offsets, rot_offsets, joint_types, joint_state_define_func_file}
+        Ok(InfoFileParser{urdf_file_name, fixed_frame, joint_names, joint_ordering, ee_fixed_joints, starting_config, collision_file_name, collision_nn_file, path_to_src, axis_types, velocity_limits,
+            joint_limits, displacements, disp_offsets, rot_offsets, joint_types, joint_state_define_func_file, tcp_offsets})
     }
 }
 
@@ -153,42 +236,17 @@ pub struct CollisionFileParser {
     pub split_point: f64
 }
 impl CollisionFileParser {
-    pub fn from_yaml_path(fp: String) -> Self {
-        let docs = get_yaml_obj(fp);
+    pub fn from_yaml_path(fp: String) -> Result<Self, RelaxedIKError> {
+        let docs = get_yaml_obj(fp.clone())?;
         let doc = &docs[0];
+        let f = fp.as_str();
 
-        let mut states: Vec<Vec<f64>> = Vec::new();
-        let mut jt_pts: Vec<Vec<f64>> = Vec::new();
-        let mut collision_scores: Vec<f64> = Vec::new();
-
-        let mut states_arr = doc["states"].as_vec().unwrap();
-        for i in 0..states_arr.len() {
-            let s: Vec<f64> = Vec::new();
-            states.push(s);
-            let states_arr_2 = states_arr[i].as_vec().unwrap();
-            for j in 0..states_arr_2.len() {
-                states[i].push( states_arr_2[j].as_f64().unwrap() )
-            }
-        }
-
-        let jt_pts_arr = doc["jt_pts"].as_vec().unwrap();
-        for i in 0..jt_pts_arr.len() {
-            let s: Vec<f64> = Vec::new();
-            jt_pts.push(s);
-            let jt_pts_arr_2 = jt_pts_arr[i].as_vec().unwrap();
-            for j in 0..jt_pts_arr_2.len() {
-                jt_pts[i].push(jt_pts_arr_2[j].as_f64().unwrap());
-            }
-        }
-
-        let collision_values_arr = doc["collision_scores"].as_vec().unwrap();
-        for i in 0..collision_values_arr.len() {
-            collision_scores.push(collision_values_arr[i].as_f64().unwrap());
-        }
-
-        let split_point = doc["split_point"].as_f64().unwrap();
+        let states = parse_list_of_floats_2(&doc["states"], f, "states")?;
+        let jt_pts = parse_list_of_floats_2(&doc["jt_pts"], f, "jt_pts")?;
+        let collision_scores = parse_list_of_floats_1(&doc["collision_scores"], f, "collision_scores")?;
+        let split_point = yaml_f64(&doc["split_point"], f, "split_point")?;
 
-        CollisionFileParser{states, jt_pts, collision_scores, split_point}
+        Ok(CollisionFileParser{states, jt_pts, collision_scores, split_point})
     }
 }
 
@@ -201,23 +259,29 @@ pub struct NeuralNetParser {
     pub split_point: f64
 }
 impl NeuralNetParser {
-    pub fn from_yaml_path(fp: String) -> Self {
-        let docs = get_yaml_obj(fp);
+    pub fn from_yaml_path(fp: String) -> Result<Self, RelaxedIKError> {
+        let docs = get_yaml_obj(fp.clone())?;
         let doc = &docs[0];
+        let f = fp.as_str();
 
-        let mut coefs: Vec<Vec<Vec<f64>>> = Vec::new();
-        let mut intercepts: Vec<Vec<f64>> = Vec::new();
         let mut coef_matrices: Vec<DMatrix<f64>> = Vec::new();
         let mut intercept_vectors: Vec<DMatrix<f64>> = Vec::new();
-        let mut split_point = 0.0;
 
-        coefs = parse_list_of_floats_3(&doc["coefs"]);
-        intercepts = parse_list_of_floats_2(&doc["intercepts"]);
-        split_point = doc["split_point"].as_f64().unwrap();
+        let coefs = parse_list_of_floats_3(&doc["coefs"], f, "coefs")?;
+        let intercepts = parse_list_of_floats_2(&doc["intercepts"], f, "intercepts")?;
+        let split_point = yaml_f64(&doc["split_point"], f, "split_point")?;
+        if coefs.len() == 0 {
+            return Err(RelaxedIKError::InvalidValue{path: fp.clone(), key: "coefs".to_string(), expected: "at least one layer".to_string()});
+        }
+        check_len(intercepts.len(), coefs.len(), f, "intercepts", "layer in coefs")?;
 
         for i in 0..coefs.len() {
+            if coefs[i].len() == 0 {
+                return Err(RelaxedIKError::InvalidValue{path: fp.clone(), key: format!("coefs[{}]", i), expected: "a non-empty matrix".to_string()});
+            }
             let mut m = DMatrix::from_element( coefs[i].len(), coefs[i][0].len(), 0.0 );
             for j in 0..coefs[i].len() {
+                check_len(coefs[i][j].len(), coefs[i][0].len(), f, format!("coefs[{}][{}]", i, j).as_str(), "column")?;
                 for k in 0..coefs[i][j].len() {
                     m[(j,k)] = coefs[i][j][k];
                 }
@@ -233,7 +297,7 @@ impl NeuralNetParser {
             intercept_vectors.push(v);
         }
 
-        Self{coefs, intercepts, coef_matrices, intercept_vectors, split_point}
+        Ok(Self{coefs, intercepts, coef_matrices, intercept_vectors, split_point})
     }
 }
 
@@ -244,13 +308,14 @@ pub struct RobotCollisionSpecFileParser {
     pub spheres: Vec<Sphere>
 }
 impl RobotCollisionSpecFileParser {
-    pub fn from_yaml_path(fp: String) -> Self {
-        let docs = get_yaml_obj(fp);
+    pub fn from_yaml_path(fp: String) -> Result<Self, RelaxedIKError> {
+        let docs = get_yaml_obj(fp.clone())?;
         let doc = &docs[0];
-        let mut cuboids_option = doc["boxes"].as_vec();
-        let mut spheres_option = doc["spheres"].as_vec();
+        let f = fp.as_str();
+        let cuboids_option = doc["boxes"].as_vec();
+        let spheres_option = doc["spheres"].as_vec();
 
-        let robot_link_radius = doc["robot_link_radius"].as_f64().unwrap();
+        let robot_link_radius = yaml_f64(&doc["robot_link_radius"], f, "robot_link_radius")?;
 
         let mut cuboids: Vec<Cuboid> = Vec::new();
         let mut spheres: Vec<Sphere> = Vec::new();
@@ -259,25 +324,14 @@ impl RobotCollisionSpecFileParser {
             let cuboids_list = cuboids_option.unwrap();
             let l = cuboids_list.len();
             for i in 0..l {
-                let name = cuboids_list[i]["name"].as_str().unwrap().to_string();
-                let params = cuboids_list[i]["parameters"].as_vec().unwrap();
-                let x_halflength = params[0].as_f64().unwrap();
-                let y_halflength = params[1].as_f64().unwrap();
-                let z_halflength = params[2].as_f64().unwrap();
-
-                let coordinate_frame = cuboids_list[i]["coordinate_frame"].as_str().unwrap().to_string();
-
-                let rots = cuboids_list[i]["rotation"].as_vec().unwrap();
-                let rx = rots[0].as_f64().unwrap();
-                let ry = rots[1].as_f64().unwrap();
-                let rz = rots[2].as_f64().unwrap();
-
-                let ts = cuboids_list[i]["translation"].as_vec().unwrap();
-                let tx = ts[0].as_f64().unwrap();
-                let ty = ts[1].as_f64().unwrap();
-                let tz = ts[2].as_f64().unwrap();
-
-                cuboids.push(Cuboid::new(name, x_halflength, y_halflength, z_halflength, coordinate_frame, rx, ry, rz, tx, ty, tz));
+                let key = format!("boxes[{}]", i);
+                let name = yaml_str(&cuboids_list[i]["name"], f, format!("{}.name", key).as_str())?;
+                let params = yaml_vector3(&cuboids_list[i]["parameters"], f, format!("{}.parameters", key).as_str())?;
+                let coordinate_frame = yaml_str(&cuboids_list[i]["coordinate_frame"], f, format!("{}.coordinate_frame", key).as_str())?;
+                let rots = yaml_vector3(&cuboids_list[i]["rotation"], f, format!("{}.rotation", key).as_str())?;
+                let ts = yaml_vector3(&cuboids_list[i]["translation"], f, format!("{}.translation", key).as_str())?;
+
+                cuboids.push(Cuboid::new(name, params[0], params[1], params[2], coordinate_frame, rots[0], rots[1], rots[2], ts[0], ts[1], ts[2]));
             }
         }
 
@@ -286,21 +340,17 @@ impl RobotCollisionSpecFileParser {
             let l = spheres_list.len();
 
             for i in 0..l {
-                let name = spheres_list[i]["name"].as_str().unwrap().to_string();
-                let radius = spheres_list[i]["parameters"].as_f64().unwrap();
+                let key = format!("spheres[{}]", i);
+                let name = yaml_str(&spheres_list[i]["name"], f, format!("{}.name", key).as_str())?;
+                let radius = yaml_f64(&spheres_list[i]["parameters"], f, format!("{}.parameters", key).as_str())?;
+                let coordinate_frame = yaml_str(&spheres_list[i]["coordinate_frame"], f, format!("{}.coordinate_frame", key).as_str())?;
+                let ts = yaml_vector3(&spheres_list[i]["translation"], f, format!("{}.translation", key).as_str())?;
 
-                let coordinate_frame = spheres_list[i]["coordinate_frame"].as_str().unwrap().to_string();
-
-                let ts = spheres_list[i]["translation"].as_vec().unwrap();
-                let tx = ts[0].as_f64().unwrap();
-                let ty = ts[1].as_f64().unwrap();
-                let tz = ts[2].as_f64().unwrap();
-
-                spheres.push(Sphere::new(name, radius, coordinate_frame, tx, ty, tz));
+                spheres.push(Sphere::new(name, radius, coordinate_frame, ts[0], ts[1], ts[2]));
             }
         }
 
-        Self{robot_link_radius, cuboids, spheres}
+        Ok(Self{robot_link_radius, cuboids, spheres})
     }
 }
 
@@ -309,48 +359,52 @@ pub struct EnvCollisionFileParser {
     pub robot_link_radius: f64,
     pub cuboids: Vec<CuboidEnv>,
     pub spheres: Vec<SphereEnv>,
-    pub pcds:Vec<PCEnv>
+    pub pcds:Vec<PCEnv>,
+    // for obstacles whose animation names a file in animation_files
+    pub animations: Vec<ObstacleAnimation>
 }
 impl EnvCollisionFileParser {
-    pub fn from_yaml_path(fp: String) -> Self {
-        let fp2 = fp.clone();
-        let docs = get_yaml_obj(fp);
-        let doc = &docs[0];
+    pub fn from_yaml_path(fp: String) -> Result<Self, RelaxedIKError> {
+        EnvCollisionFileParser::from_yaml_path_with_paths(fp, &ConfigPaths::from_env())
+    }
+
+    // point cloud files are looked up in paths.geometry_dir, animation files in paths.config_dir/animation_files.
+    // an obstacle's animation is static, interactive (moved through update_dynamic_obstacle) or the name of an animation file
+    pub fn from_yaml_path_with_paths(fp: String, paths: &ConfigPaths) -> Result<Self, RelaxedIKError> {
+        let docs = get_yaml_obj(fp.clone())?;
+        EnvCollisionFileParser::from_yaml_doc(&docs[0], fp.as_str(), paths)
+    }
 
+    // doc is an already parsed settings file, f its path for error messages
+    pub fn from_yaml_doc(doc: &Yaml, f: &str, paths: &ConfigPaths) -> Result<Self, RelaxedIKError> {
         let cuboids_option = doc["obstacles"]["cuboids"].as_vec();
         let spheres_option = doc["obstacles"]["spheres"].as_vec();
         let point_cloud_option = doc["obstacles"]["point_cloud"].as_vec();
 
-        let robot_link_radius = doc["loaded_robot"]["link_radius"].as_f64().unwrap();
+        let robot_link_radius = yaml_f64(&doc["loaded_robot"]["link_radius"], f, "loaded_robot.link_radius")?;
 
         let mut cuboids: Vec<CuboidEnv> = Vec::new();
         let mut spheres: Vec<SphereEnv> = Vec::new();
         let mut pcds: Vec<PCEnv> = Vec::new();
+        let mut animations: Vec<ObstacleAnimation> = Vec::new();
 
         if cuboids_option.is_some() {
             let cuboids_list = cuboids_option.unwrap();
             let l = cuboids_list.len();
             for i in 0..l {
-                let name = cuboids_list[i]["name"].as_str().unwrap().to_string();
-                let scale = cuboids_list[i]["scale"].as_vec().unwrap();
-                let sx_half = scale[0].as_f64().unwrap();
-                let sy_half = scale[1].as_f64().unwrap();
-                let sz_half = scale[2].as_f64().unwrap();
-
-                let rots = cuboids_list[i]["rotation"].as_vec().unwrap();
-                let rx = rots[0].as_f64().unwrap();
-                let ry = rots[1].as_f64().unwrap();
-                let rz = rots[2].as_f64().unwrap();
-
-                let ts = cuboids_list[i]["translation"].as_vec().unwrap();
-                let tx = ts[0].as_f64().unwrap();
-                let ty = ts[1].as_f64().unwrap();
-                let tz = ts[2].as_f64().unwrap();
-
-                let animation = cuboids_list[i]["animation"].as_str().unwrap();
+                let key = format!("obstacles.cuboids[{}]", i);
+                let name = yaml_str(&cuboids_list[i]["name"], f, format!("{}.name", key).as_str())?;
+                let scale = yaml_vector3(&cuboids_list[i]["scale"], f, format!("{}.scale", key).as_str())?;
+                let rots = yaml_vector3(&cuboids_list[i]["rotation"], f, format!("{}.rotation", key).as_str())?;
+                let ts = yaml_vector3(&cuboids_list[i]["translation"], f, format!("{}.translation", key).as_str())?;
+
+                let animation = yaml_str(&cuboids_list[i]["animation"], f, format!("{}.animation", key).as_str())?;
                 let is_dynamic = animation != "static";
+                if is_dynamic && animation != "interactive" {
+                    animations.push(load_obstacle_animation(name.clone(), animation.as_str(), paths)?);
+                }
 
-                cuboids.push(CuboidEnv::new(name, sx_half, sy_half, sz_half, rx, ry, rz, tx, ty, tz, is_dynamic));
+                cuboids.push(CuboidEnv::new(name, scale[0], scale[1], scale[2], rots[0], rots[1], rots[2], ts[0], ts[1], ts[2], is_dynamic));
             }
         }
 
@@ -358,18 +412,18 @@ impl EnvCollisionFileParser {
             let spheres_list = spheres_option.unwrap();
             let l = spheres_list.len();
             for i in 0..l {
-                let name = spheres_list[i]["name"].as_str().unwrap().to_string();
-                let radius = spheres_list[i]["scale"].as_f64().unwrap();
-
-                let ts = spheres_list[i]["translation"].as_vec().unwrap();
-                let tx = ts[0].as_f64().unwrap();
-                let ty = ts[1].as_f64().unwrap();
-                let tz = ts[2].as_f64().unwrap();
+                let key = format!("obstacles.spheres[{}]", i);
+                let name = yaml_str(&spheres_list[i]["name"], f, format!("{}.name", key).as_str())?;
+                let radius = yaml_f64(&spheres_list[i]["scale"], f, format!("{}.scale", key).as_str())?;
+                let ts = yaml_vector3(&spheres_list[i]["translation"], f, format!("{}.translation", key).as_str())?;
 
-                let animation = spheres_list[i]["animation"].as_str().unwrap();
+                let animation = yaml_str(&spheres_list[i]["animation"], f, format!("{}.animation", key).as_str())?;
                 let is_dynamic = animation != "static";
+                if is_dynamic && animation != "interactive" {
+                    animations.push(load_obstacle_animation(name.clone(), animation.as_str(), paths)?);
+                }
 
-                spheres.push(SphereEnv::new(name, radius, tx, ty, tz, is_dynamic));
+                spheres.push(SphereEnv::new(name, radius, ts[0], ts[1], ts[2], is_dynamic));
             }
         }
 
@@ -378,31 +432,23 @@ impl EnvCollisionFileParser {
             let l = point_cloud_list.len();
 
             for i in 0..l {
-                let name = point_cloud_list[i]["name"].as_str().unwrap().to_string();
-                let animation = point_cloud_list[i]["animation"].as_str().unwrap();
+                let key = format!("obstacles.point_cloud[{}]", i);
+                let name = yaml_str(&point_cloud_list[i]["name"], f, format!("{}.name", key).as_str())?;
+                let animation = yaml_str(&point_cloud_list[i]["animation"], f, format!("{}.animation", key).as_str())?;
                 let is_dynamic = animation != "static";
+                if is_dynamic && animation != "interactive" {
+                    animations.push(load_obstacle_animation(name.clone(), animation.as_str(), paths)?);
+                }
 
-                let scale = point_cloud_list[i]["scale"].as_vec().unwrap();
-                let sx = scale[0].as_f64().unwrap();
-                let sy = scale[1].as_f64().unwrap();
-                let sz = scale[2].as_f64().unwrap();
-
-                let ts = point_cloud_list[i]["translation"].as_vec().unwrap();
-                let tx = ts[0].as_f64().unwrap();
-                let ty = ts[1].as_f64().unwrap();
-                let tz = ts[2].as_f64().unwrap();
-
-                let rots = point_cloud_list[i]["rotation"].as_vec().unwrap();
-                let rx = rots[0].as_f64().unwrap();
-                let ry = rots[1].as_f64().unwrap();
-                let rz = rots[2].as_f64().unwrap();
+                let scale = yaml_vector3(&point_cloud_list[i]["scale"], f, format!("{}.scale", key).as_str())?;
+                let ts = yaml_vector3(&point_cloud_list[i]["translation"], f, format!("{}.translation", key).as_str())?;
+                let rots = yaml_vector3(&point_cloud_list[i]["rotation"], f, format!("{}.rotation", key).as_str())?;
 
                 let mut points: Vec<SphereEnv> = Vec::new();
-                let path_to_src_str = get_path_to_src();
-                let path_to_src = Path::new(&path_to_src_str);
-                let point_cloud_file_name = point_cloud_list[i]["file"].as_str().unwrap();
-                let point_cloud_path = path_to_src.join(Path::new("geometry_files")).join(Path::new(point_cloud_file_name));
-                let point_cloud_file = File::open(point_cloud_path).unwrap();
+                let point_cloud_file_name = yaml_str(&point_cloud_list[i]["file"], f, format!("{}.file", key).as_str())?;
+                let point_cloud_path = paths.geometry_file(point_cloud_file_name.as_str());
+                let point_cloud_file = File::open(&point_cloud_path)
+                    .map_err(|e| RelaxedIKError::Io{path: point_cloud_path.clone(), source: e})?;
                 let lines: Vec<Result<String, io::Error>> = io::BufReader::new(point_cloud_file).lines().collect();
                 for line in lines {
                     if let Ok(l) = line {
@@ -416,71 +462,197 @@ impl EnvCollisionFileParser {
                             let y = data[1].parse::<f64>().unwrap();
                             let z = data[2].parse::<f64>().unwrap();
                             // println!("Point: ({}, {}, {})", x, y, z);
-                            points.push(SphereEnv::new(name.clone(), 0.001, sx * x, sy * y, sz * z, false));
+                            points.push(SphereEnv::new(name.clone(), 0.001, scale[0] * x, scale[1] * y, scale[2] * z, false));
                         }
                     }
                 }
-                pcds.push(PCEnv::new(name, rx, ry, rz, tx, ty, tz, is_dynamic, points))
+                pcds.push(PCEnv::new(name, rots[0], rots[1], rots[2], ts[0], ts[1], ts[2], is_dynamic, points))
             }
         }
 
-        Self{robot_link_radius, cuboids, spheres, pcds}
+        Ok(Self{robot_link_radius, cuboids, spheres, pcds, animations})
+    }
+}
+
+fn load_obstacle_animation(name: String, animation: &str, paths: &ConfigPaths) -> Result<ObstacleAnimation, RelaxedIKError> {
+    let trajectory = CartesianTrajectory::from_path(paths.animation_file(animation))?;
+    ObstacleAnimation::new(name, trajectory)
+}
+
+// everything the solver and relaxed_ik_bin read from settings.yaml, parsed in one go so a load never mixes two
+// versions of the file
+#[derive(Clone, Debug)]
+pub struct SettingsParser {
+    pub info_file_name: String,
+    pub objective_mode: String,
+    // optional, settings files written before this option existed leave it off
+    pub avoid_singularities: bool,
+    // absolute, relative, relative_to_current or relative_to_tool, see GoalFrame. optional, defaults to relative
+    pub position_mode: GoalFrame,
+    pub rotation_mode: GoalFrame,
+    // keyboard, or the name of a file in animation_files to play back. optional, defaults to keyboard
+    pub input_device: String,
+    // solves per second of trajectory time when playing back an animation file. optional, defaults to 100
+    pub playback_rate: f64,
+    // where a playback writes its joint trajectory. optional, defaults to joint_trajectory.csv in the working directory
+    pub trajectory_output: String,
+    // optional, without an objectives list the objective_mode preset is used
+    pub objective_stack: Option<Vec<ObjectiveSpec>>,
+    pub env_collision: EnvCollisionFileParser
+}
+impl SettingsParser {
+    pub fn from_yaml_path(fp: String) -> Result<Self, RelaxedIKError> {
+        SettingsParser::from_yaml_path_with_paths(fp, &ConfigPaths::from_env())
+    }
+
+    // paths is only used to find the obstacles' point cloud and animation files
+    pub fn from_yaml_path_with_paths(fp: String, paths: &ConfigPaths) -> Result<Self, RelaxedIKError> {
+        let docs = get_yaml_obj(fp.clone())?;
+        let doc = &docs[0];
+        let f = fp.as_str();
+        let robot = &doc["loaded_robot"];
+
+        let info_file_name = yaml_str(&robot["name"], f, "loaded_robot.name")?;
+        let objective_mode = yaml_str(&robot["objective_mode"], f, "loaded_robot.objective_mode")?;
+        let avoid_singularities = if robot["avoid_singularities"].is_badvalue() { false } else { yaml_bool(&robot["avoid_singularities"], f, "loaded_robot.avoid_singularities")? };
+        let position_mode = yaml_goal_frame(&robot["position_mode"], f, "loaded_robot.position_mode")?;
+        let rotation_mode = yaml_goal_frame(&robot["rotation_mode"], f, "loaded_robot.rotation_mode")?;
+        let input_device = if robot["input_device"].is_badvalue() { "keyboard".to_string() } else { yaml_str(&robot["input_device"], f, "loaded_robot.input_device")? };
+        let playback_rate = if robot["playback_rate"].is_badvalue() { 100.0 } else { yaml_f64(&robot["playback_rate"], f, "loaded_robot.playback_rate")? };
+        let trajectory_output = if robot["trajectory_output"].is_badvalue() { "joint_trajectory.csv".to_string() } else { yaml_str(&robot["trajectory_output"], f, "loaded_robot.trajectory_output")? };
+        let objective_stack = if doc["objectives"].is_badvalue() { None } else { Some(yaml_objective_stack(&doc["objectives"], f, "objectives")?) };
+        let env_collision = EnvCollisionFileParser::from_yaml_doc(doc, f, paths)?;
+
+        Ok(Self{info_file_name, objective_mode, avoid_singularities, position_mode, rotation_mode, input_device, playback_rate, trajectory_output,
+            objective_stack, env_collision})
     }
 }
 
-pub fn get_objective_mode(fp: String) -> String {
-    let yaml_files = get_yaml_obj(fp);
-    let yaml_file = &yaml_files[0];
+// a GoalFrame name, relative when left out
+fn yaml_goal_frame(y: &Yaml, fp: &str, key: &str) -> Result<GoalFrame, RelaxedIKError> {
+    if y.is_badvalue() {
+        return Ok(GoalFrame::RelativeToStart);
+    }
+    let name = yaml_str(y, fp, key)?;
+    match GoalFrame::from_name(name.as_str()) {
+        Some(frame) => Ok(frame),
+        None => Err(RelaxedIKError::InvalidValue{path: fp.to_string(), key: key.to_string(), expected: format!("absolute, relative, relative_to_current or relative_to_tool, got {}", name)})
+    }
+}
 
-    let objective_mode = yaml_file["loaded_robot"]["objective_mode"].as_str().unwrap().to_string();
-    objective_mode
+// one entry of an objectives list, see settings.yaml. kind names an objective type, chain is only used by
+// the per-chain kinds (left out, the entry stands for every chain). groove_loss overrides the kind's default.
+// other_chain, position_weight and rotation_weight are only used by match_relative_pose
+#[derive(Clone, Debug)]
+pub struct ObjectiveSpec {
+    pub name: Option<String>,
+    pub kind: String,
+    pub chain: Option<usize>,
+    pub weight: f64,
+    pub groove_loss: Option<GrooveLossParams>,
+    pub other_chain: Option<usize>,
+    pub position_weight: Option<f64>,
+    pub rotation_weight: Option<f64>
 }
 
-pub fn get_info_file_name(fp: String) -> String {
-    let yaml_files = get_yaml_obj(fp);
-    let yaml_file = &yaml_files[0];
+// the objectives list of settings.yaml, one ObjectiveSpec per entry
+pub fn yaml_objective_stack(y: &Yaml, f: &str, key: &str) -> Result<Vec<ObjectiveSpec>, RelaxedIKError> {
+    let objectives_list = yaml_vec(y, f, key)?;
+    let mut out: Vec<ObjectiveSpec> = Vec::new();
+    for i in 0..objectives_list.len() {
+        let key = format!("{}[{}]", key, i);
+        let y = &objectives_list[i];
+        let kind = yaml_str(&y["kind"], f, format!("{}.kind", key).as_str())?;
+        let weight = yaml_f64(&y["weight"], f, format!("{}.weight", key).as_str())?;
+        let name = if y["name"].is_badvalue() { None } else { Some(yaml_str(&y["name"], f, format!("{}.name", key).as_str())?) };
+        let chain = if y["chain"].is_badvalue() { None } else { Some(yaml_usize(&y["chain"], f, format!("{}.chain", key).as_str())?) };
+        let groove_loss = if y["groove_loss"].is_badvalue() { None } else { Some(yaml_groove_loss(&y["groove_loss"], f, format!("{}.groove_loss", key).as_str())?) };
+        let other_chain = if y["other_chain"].is_badvalue() { None } else { Some(yaml_usize(&y["other_chain"], f, format!("{}.other_chain", key).as_str())?) };
+        let position_weight = if y["position_weight"].is_badvalue() { None } else { Some(yaml_f64(&y["position_weight"], f, format!("{}.position_weight", key).as_str())?) };
+        let rotation_weight = if y["rotation_weight"].is_badvalue() { None } else { Some(yaml_f64(&y["rotation_weight"], f, format!("{}.rotation_weight", key).as_str())?) };
+        out.push(ObjectiveSpec{name, kind, chain, weight, groove_loss, other_chain, position_weight, rotation_weight});
+    }
+    Ok(out)
+}
+
+// {translation: [x, y, z], rotation: [roll, pitch, yaw]}, rotation is optional
+pub fn yaml_isometry(y: &Yaml, fp: &str, key: &str) -> Result<nalgebra::Isometry3<f64>, RelaxedIKError> {
+    let ts = yaml_vector3(&y["translation"], fp, format!("{}.translation", key).as_str())?;
+    let rot = if y["rotation"].is_badvalue() {
+        nalgebra::UnitQuaternion::identity()
+    } else {
+        let rots = yaml_vector3(&y["rotation"], fp, format!("{}.rotation", key).as_str())?;
+        nalgebra::UnitQuaternion::from_euler_angles(rots[0], rots[1], rots[2])
+    };
+    Ok(nalgebra::Isometry3::from_parts(nalgebra::Translation3::from(ts), rot))
+}
+
+// {t, d, c, f, g}, the arguments of groove_loss
+pub fn yaml_groove_loss(y: &Yaml, fp: &str, key: &str) -> Result<GrooveLossParams, RelaxedIKError> {
+    let t = yaml_f64(&y["t"], fp, format!("{}.t", key).as_str())?;
+    let d = yaml_usize(&y["d"], fp, format!("{}.d", key).as_str())?;
+    let c = yaml_f64(&y["c"], fp, format!("{}.c", key).as_str())?;
+    let f = yaml_f64(&y["f"], fp, format!("{}.f", key).as_str())?;
+    let g = yaml_usize(&y["g"], fp, format!("{}.g", key).as_str())?;
+    Ok(GrooveLossParams::new(t, d as i32, c, f, g as i32))
+}
+
+pub fn parse_list_of_strings_1(y: &Yaml, fp: &str, key: &str) -> Result<Vec<String>, RelaxedIKError> {
+    let mut ret: Vec<String> = Vec::new();
 
-    let info_file_name = yaml_file["loaded_robot"]["name"].as_str().unwrap().to_string();
-    info_file_name
+    let v1 = yaml_vec(y, fp, key)?;
+    for i in 0..v1.len() {
+        ret.push( yaml_str(&v1[i], fp, format!("{}[{}]", key, i).as_str())? );
+    }
+    Ok(ret)
 }
 
+pub fn parse_list_of_strings_2(y: &Yaml, fp: &str, key: &str) -> Result<Vec<Vec<String>>, RelaxedIKError> {
+    let mut ret: Vec<Vec<String>> = Vec::new();
+
+    let v1 = yaml_vec(y, fp, key)?;
+    for i in 0..v1.len() {
+        ret.push( parse_list_of_strings_1(&v1[i], fp, format!("{}[{}]", key, i).as_str())? );
+    }
+    Ok(ret)
+}
 
-pub fn parse_list_of_floats_1(y: &Yaml) -> Vec<f64> {
+pub fn parse_list_of_floats_1(y: &Yaml, fp: &str, key: &str) -> Result<Vec<f64>, RelaxedIKError> {
     let mut ret: Vec<f64> = Vec::new();
 
-    let v1 = y.as_vec().unwrap();
+    let v1 = yaml_vec(y, fp, key)?;
     for i in 0..v1.len() {
-        ret.push(v1[i].as_f64().unwrap());
+        ret.push( yaml_f64(&v1[i], fp, format!("{}[{}]", key, i).as_str())? );
     }
-    ret
+    Ok(ret)
 }
 
-pub fn parse_list_of_floats_2(y: &Yaml) -> Vec<Vec<f64>> {
+pub fn parse_list_of_floats_2(y: &Yaml, fp: &str, key: &str) -> Result<Vec<Vec<f64>>, RelaxedIKError> {
     let mut ret: Vec<Vec<f64>> = Vec::new();
 
-    let v1 = y.as_vec().unwrap();
+    let v1 = yaml_vec(y, fp, key)?;
     for i in 0..v1.len() {
-        ret.push( parse_list_of_floats_1(&v1[i]) );
+        ret.push( parse_list_of_floats_1(&v1[i], fp, format!("{}[{}]", key, i).as_str())? );
     }
-    ret
+    Ok(ret)
 }
 
-pub fn parse_list_of_floats_3(y: &Yaml) -> Vec<Vec<Vec<f64>>> {
+pub fn parse_list_of_floats_3(y: &Yaml, fp: &str, key: &str) -> Result<Vec<Vec<Vec<f64>>>, RelaxedIKError> {
     let mut ret: Vec<Vec<Vec<f64>>> = Vec::new();
 
-    let v1 = y.as_vec().unwrap();
+    let v1 = yaml_vec(y, fp, key)?;
     for i in 0..v1.len() {
-        ret.push( parse_list_of_floats_2(&v1[i]) );
+        ret.push( parse_list_of_floats_2(&v1[i], fp, format!("{}[{}]", key, i).as_str())? );
     }
-    ret
+    Ok(ret)
 }
 
-pub fn parse_list_of_floats_4(y: &Yaml) -> Vec<Vec<Vec<Vec<f64>>>> {
+pub fn parse_list_of_floats_4(y: &Yaml, fp: &str, key: &str) -> Result<Vec<Vec<Vec<Vec<f64>>>>, RelaxedIKError> {
     let mut ret: Vec<Vec<Vec<Vec<f64>>>> = Vec::new();
 
-    let v1 = y.as_vec().unwrap();
+    let v1 = yaml_vec(y, fp, key)?;
     for i in 0..v1.len() {
-        ret.push( parse_list_of_floats_3(&v1[i]) );
+        ret.push( parse_list_of_floats_3(&v1[i], fp, format!("{}[{}]", key, i).as_str())? );
     }
-    ret
+    Ok(ret)
 }
\ No newline at end of file